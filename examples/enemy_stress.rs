@@ -0,0 +1,200 @@
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+// Benchmark scene for big hordes: starts a run straight away and keeps adding enemies, 500 more
+// every few seconds until there are 6,000, logging the average frame time at every step next to
+// how it compares with the first 500. At the end it lists every step and says whether the frame
+// time stayed flat, i.e. no step took more than `MAX_SLOWDOWN` times the baseline, and panics if
+// it didn't so a script running it fails.
+// The player can't die and the light doesn't hurt, so the count only goes up. Enemies are placed
+// from the run's seed, `STRESS_SEED` unless `--seed` picks another, so every run sees the same
+// layout.
+//
+//     cargo run --release --example enemy_stress
+use bevy::{app::AppExit, prelude::*};
+use rand::Rng;
+use game::{
+    arena::ArenaBounds,
+    camera::CameraController,
+    enemy::{self, Enemy, EnemyAssets, EnemyKind, EnemyModifiers},
+    game::GamePlugin,
+    health::Health,
+    light::LightAura,
+    player::Player,
+    rng::{GameRng, GameSeed},
+    wave::{WaveSchedule, WaveScheduleHandle},
+    GameState,
+};
+
+const ENEMY_STEP: usize = 500;
+const MAX_ENEMIES: usize = 6000;
+const STEP_SECONDS: f32 = 5.0;
+// How much slower than with the first step's enemies a frame may get and still count as flat
+const MAX_SLOWDOWN: f32 = 1.5;
+// Seed for the enemy layout when `--seed` isn't given
+const STRESS_SEED: u64 = 6000;
+// Leave some room around the player, like the waves do
+const MIN_SPAWN_DISTANCE: f32 = 150.0;
+// Everything but the boss, which brings its own encounter along
+const KINDS: [EnemyKind; 6] = [
+    EnemyKind::Kobold,
+    EnemyKind::BlueSlime,
+    EnemyKind::GreenSlime,
+    EnemyKind::OrangeSlime,
+    EnemyKind::Fox,
+    EnemyKind::Worm,
+];
+
+fn main() {
+    let mut seed = GameSeed::from_args();
+    seed.requested.get_or_insert(STRESS_SEED);
+    App::new()
+        .add_plugins((DefaultPlugins, GamePlugin))
+        .insert_resource(seed)
+        .init_resource::<Stress>()
+        .add_systems(Startup, setup)
+        .add_systems(Update, start_run.run_if(not(in_state(GameState::Game))))
+        .add_systems(OnEnter(GameState::Game), make_player_harmless)
+        .add_systems(Update, (keep_player_alive, fill_arena, log_frame_time).run_if(in_state(GameState::Game)))
+        .run();
+}
+
+#[derive(Resource)]
+struct Stress {
+    target: usize,
+    step: Timer,
+    frames: u32,
+    seconds: f32,
+    // Average frame time in milliseconds with `ENEMY_STEP` enemies
+    baseline: Option<f32>,
+    // Enemy count and average frame time of every step so far
+    timings: Vec<(usize, f32)>,
+}
+
+impl Default for Stress {
+    fn default() -> Self {
+        Stress {
+            target: ENEMY_STEP,
+            step: Timer::from_seconds(STEP_SECONDS, TimerMode::Repeating),
+            frames: 0,
+            seconds: 0.0,
+            baseline: None,
+            timings: Vec::new(),
+        }
+    }
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn((Camera2dBundle::default(), CameraController::default()));
+}
+
+// There is no menu in this scene, go through it to set the run up
+fn start_run(
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut schedules: ResMut<Assets<WaveSchedule>>,
+) {
+    match state.get() {
+        GameState::Menu => {
+            // Only the enemies spawned here, no waves on top
+            let schedule = schedules.add(WaveSchedule { min_spawn_distance: 0.0, waves: vec![] });
+            commands.insert_resource(WaveScheduleHandle(schedule));
+            next_state.set(GameState::Game);
+        }
+        GameState::Splash => next_state.set(GameState::Menu),
+        _ => {}
+    }
+}
+
+fn make_player_harmless(mut query: Query<&mut LightAura, With<Player>>) {
+    for mut aura in query.iter_mut() {
+        aura.damage = 0;
+    }
+}
+
+fn keep_player_alive(mut query: Query<&mut Health, With<Player>>) {
+    for mut health in query.iter_mut() {
+        health.hit_points = health.max_hit_points;
+    }
+}
+
+// Tops the arena back up to the target count, enemies touching the player are gone
+fn fill_arena(
+    mut commands: Commands,
+    enemy_assets: Res<EnemyAssets>,
+    bounds: Res<ArenaBounds>,
+    stress: Res<Stress>,
+    mut rng: ResMut<GameRng>,
+    player_query: Query<&Transform, With<Player>>,
+    enemy_query: Query<(), With<Enemy>>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    let player_position = player_transform.translation.truncate();
+    let mut count = enemy_query.iter().count();
+    while count < stress.target {
+        let position = Vec2::new(
+            rng.gen_range(bounds.left..bounds.right),
+            rng.gen_range(bounds.bottom..bounds.top),
+        );
+        if position.distance(player_position) < MIN_SPAWN_DISTANCE {
+            continue;
+        }
+        let kind = KINDS[count % KINDS.len()];
+        enemy::spawn_enemy(&mut commands, &enemy_assets, kind, position, EnemyModifiers::default());
+        count += 1;
+    }
+}
+
+fn log_frame_time(
+    time: Res<Time>,
+    mut stress: ResMut<Stress>,
+    mut exit_events: EventWriter<AppExit>,
+    enemy_query: Query<(), With<Enemy>>,
+) {
+    stress.frames += 1;
+    stress.seconds += time.delta_seconds();
+    if !stress.step.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let count = enemy_query.iter().count();
+    let frame_time = stress.seconds * 1000.0 / stress.frames as f32;
+    let baseline = *stress.baseline.get_or_insert(frame_time);
+    info!(
+        "{} enemies: {:.2} ms per frame, {:.2}x the {} enemy baseline",
+        count,
+        frame_time,
+        frame_time / baseline,
+        ENEMY_STEP,
+    );
+    stress.timings.push((count, frame_time));
+    stress.frames = 0;
+    stress.seconds = 0.0;
+    if stress.target >= MAX_ENEMIES {
+        report(&stress.timings, baseline);
+        exit_events.send(AppExit);
+        return;
+    }
+    stress.target += ENEMY_STEP;
+}
+
+fn report(timings: &[(usize, f32)], baseline: f32) {
+    for (count, frame_time) in timings {
+        info!("{:>5} enemies  {:>7.2} ms  {:>5.2}x", count, frame_time, frame_time / baseline);
+    }
+    let (worst_count, worst_time) = timings
+        .iter()
+        .copied()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((0, baseline));
+    let slowdown = worst_time / baseline;
+    if slowdown <= MAX_SLOWDOWN {
+        info!("Frame time stayed flat: at most {:.2}x the baseline, with {} enemies", slowdown, worst_count);
+    } else {
+        panic!(
+            "Frame time did not stay flat: {:.2}x the baseline with {} enemies, more than {:.2}x",
+            slowdown, worst_count, MAX_SLOWDOWN
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use bevy::prelude::*;
+
+use crate::{despawn_with_component, health::Damaged, player::Player, GameState};
+
+// This plugin plays sprite sheet animations. Every animated sprite carries its own named clips:
+// movement picks between idle and run, getting hit plays hurt on top of that, and
+// death plays once and stays on its last frame.
+// It keeps running on the game over screen, so the player can be seen dying.
+pub struct SpriteAnimationPlugin;
+
+impl Plugin for SpriteAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (play_hurt_clips, animate_sprites, despawn_finished_animations)
+                .chain()
+                .run_if(in_state(GameState::Game).or_else(in_state(GameState::GameLost))))
+            .add_systems(OnEnter(GameState::GameLost), play_player_death)
+            .add_systems(OnEnter(GameState::Menu), despawn_with_component::<DespawnWhenFinished>);
+    }
+}
+
+const HURT_TINT: Color = Color::rgb(1.0, 0.25, 0.25);
+const DEATH_TINT: Color = Color::rgba(0.4, 0.1, 0.1, 0.6);
+const HURT_SECONDS: f32 = 0.2;
+const DEATH_SECONDS: f32 = 0.6;
+// Slower than this counts as standing still
+const MIN_RUN_SPEED: f32 = 0.01;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationState {
+    Idle,
+    Run,
+    Hurt,
+    Death,
+}
+
+// A range of frames in a texture atlas, optionally drawn with a tint
+#[derive(Debug, Clone, Copy)]
+pub struct Clip {
+    pub first: usize,
+    pub last: usize,
+    pub fps: f32,
+    pub tint: Option<Color>,
+}
+
+impl Clip {
+    pub const fn new(first: usize, last: usize, fps: f32) -> Self {
+        Clip { first, last, fps, tint: None }
+    }
+
+    // A clip that holds a single frame for `seconds`
+    pub const fn hold(frame: usize, seconds: f32) -> Self {
+        Clip { first: frame, last: frame, fps: 1.0 / seconds, tint: None }
+    }
+
+    pub const fn tinted(self, tint: Color) -> Self {
+        Clip { tint: Some(tint), ..self }
+    }
+
+    fn len(&self) -> usize {
+        self.last - self.first + 1
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AnimationClips {
+    pub idle: Clip,
+    pub run: Clip,
+    pub hurt: Clip,
+    pub death: Clip,
+}
+
+impl AnimationClips {
+    // Our sheets only have idle and run frames, hurt and death flash the first idle frame instead
+    pub const fn idle_run(idle: Clip, run: Clip) -> Self {
+        AnimationClips {
+            idle,
+            run,
+            hurt: Clip::hold(idle.first, HURT_SECONDS).tinted(HURT_TINT),
+            death: Clip::hold(idle.first, DEATH_SECONDS).tinted(DEATH_TINT),
+        }
+    }
+
+    fn get(&self, state: AnimationState) -> Clip {
+        match state {
+            AnimationState::Idle => self.idle,
+            AnimationState::Run => self.run,
+            AnimationState::Hurt => self.hurt,
+            AnimationState::Death => self.death,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct SpriteAnimation {
+    clips: AnimationClips,
+    state: AnimationState,
+    // Frame of the current clip, counted from its first frame
+    frame: usize,
+    frame_timer: Timer,
+    // Set once a hurt or death clip has shown its last frame
+    finished: bool,
+    moving: bool,
+    facing_left: bool,
+    // Color the sprite is drawn with when the clip has no tint
+    pub base_color: Color,
+}
+
+impl SpriteAnimation {
+    pub fn new(clips: AnimationClips) -> Self {
+        SpriteAnimation {
+            clips,
+            state: AnimationState::Idle,
+            frame: 0,
+            frame_timer: Self::frame_timer(clips.idle),
+            finished: false,
+            moving: false,
+            facing_left: false,
+            base_color: Color::WHITE,
+        }
+    }
+
+    // The same animation, playing death from the start, e.g. for what is left of a killed enemy
+    pub fn dying(&self) -> Self {
+        let mut animation = SpriteAnimation {
+            facing_left: self.facing_left,
+            base_color: self.base_color,
+            ..SpriteAnimation::new(self.clips)
+        };
+        animation.play(AnimationState::Death);
+        animation
+    }
+
+    pub fn state(&self) -> AnimationState {
+        self.state
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // Called by the movement systems with how the sprite moves this tick
+    pub fn set_movement(&mut self, velocity: Vec2) {
+        self.moving = velocity.length() > MIN_RUN_SPEED;
+        if velocity.x < -MIN_RUN_SPEED {
+            self.facing_left = true;
+        } else if velocity.x > MIN_RUN_SPEED {
+            self.facing_left = false;
+        }
+    }
+
+    // Starts a clip from its first frame, nothing plays after death
+    pub fn play(&mut self, state: AnimationState) {
+        if self.state == AnimationState::Death {
+            return;
+        }
+        self.state = state;
+        self.frame = 0;
+        self.finished = false;
+        self.frame_timer = Self::frame_timer(self.clips.get(state));
+    }
+
+    fn frame_timer(clip: Clip) -> Timer {
+        Timer::from_seconds(1.0 / clip.fps, TimerMode::Repeating)
+    }
+
+    fn movement_state(&self) -> AnimationState {
+        if self.moving {
+            AnimationState::Run
+        } else {
+            AnimationState::Idle
+        }
+    }
+
+    fn advance(&mut self, delta: std::time::Duration) {
+        match self.state {
+            AnimationState::Idle | AnimationState::Run => {
+                let wanted = self.movement_state();
+                if wanted != self.state {
+                    self.play(wanted);
+                }
+            }
+            AnimationState::Hurt if self.finished => self.play(self.movement_state()),
+            _ => {}
+        }
+
+        let clip = self.clips.get(self.state);
+        let frames = self.frame_timer.tick(delta).times_finished_this_tick() as usize;
+        match self.state {
+            AnimationState::Idle | AnimationState::Run => {
+                self.frame = (self.frame + frames) % clip.len();
+            }
+            AnimationState::Hurt | AnimationState::Death => {
+                // A clip that plays once is over after its last frame was shown for a full frame
+                if self.frame + frames >= clip.len() {
+                    self.frame = clip.len() - 1;
+                    self.finished = true;
+                } else {
+                    self.frame += frames;
+                }
+            }
+        }
+    }
+}
+
+// Sprites with this are despawned once their clip is over
+#[derive(Component)]
+pub struct DespawnWhenFinished;
+
+
+fn play_hurt_clips(mut damaged_events: EventReader<Damaged>, mut query: Query<&mut SpriteAnimation>) {
+    for damaged in damaged_events.read() {
+        if let Ok(mut animation) = query.get_mut(damaged.entity) {
+            animation.play(AnimationState::Hurt);
+        }
+    }
+}
+
+fn animate_sprites(
+    time: Res<Time>,
+    mut query: Query<(&mut SpriteAnimation, &mut TextureAtlasSprite)>,
+) {
+    for (mut animation, mut sprite) in query.iter_mut() {
+        animation.advance(time.delta());
+
+        let clip = animation.clips.get(animation.state);
+        let index = clip.first + animation.frame;
+        if sprite.index != index {
+            sprite.index = index;
+        }
+        if sprite.flip_x != animation.facing_left {
+            sprite.flip_x = animation.facing_left;
+        }
+        let color = clip.tint.unwrap_or(animation.base_color);
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+fn despawn_finished_animations(
+    mut commands: Commands,
+    query: Query<(Entity, &SpriteAnimation), With<DespawnWhenFinished>>,
+) {
+    for (entity, animation) in query.iter() {
+        if animation.is_finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn play_player_death(mut query: Query<&mut SpriteAnimation, With<Player>>) {
+    for mut animation in query.iter_mut() {
+        animation.play(AnimationState::Death);
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    reflect::TypePath,
+    utils::{thiserror::{self, Error}, BoxedFuture},
+};
+use rand::Rng;
+use serde::Deserialize;
+use crate::{
+    despawn_with_component,
+    obstacle::{self, PropKind},
+    game::{LEFT_WALL, RIGHT_WALL, TOP_WALL, BOTTOM_WALL},
+    rng::{self, GameRng},
+    GameState,
+};
+
+// This plugin draws the arena from the tile layouts in `assets/arenas/`, one picked at random
+// for each run. A layout is a grid of ground tiles with a ring of wall tiles drawn around it,
+// an optional layer of decorations on top and one of obstacles, see `obstacle::PropKind`.
+// The area inside the walls is where everything happens.
+pub struct ArenaPlugin;
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_asset::<ArenaLayout>()
+        .init_asset_loader::<ArenaLayoutLoader>()
+        .init_resource::<ArenaBounds>()
+        .init_resource::<ArenaExtent>()
+        .add_systems(Startup, load_arena_layouts)
+        .add_systems(OnExit(GameState::Menu), pick_arena.after(rng::seed_run))
+        .add_systems(OnEnter(GameState::Menu), (despawn_with_component::<OnArena>, reset_arena))
+        .add_systems(Update, spawn_arena.run_if(resource_exists::<CurrentArena>()));
+    }
+}
+
+const ARENA_PATHS: [&str; 3] = [
+    "arenas/meadow.arena.ron",
+    "arenas/crossroads.arena.ron",
+    "arenas/garden.arena.ron",
+];
+// Wall thickness before the layout of the run is loaded, the tile size of the layouts
+const DEFAULT_WALL_THICKNESS: f32 = 32.0;
+// Size of the tiles in the texture files, decorations are scaled up as much as the tiles are
+const SOURCE_TILE_SIZE: f32 = 16.0;
+const GROUND_LAYER: f32 = -10.0;
+const DECORATION_LAYER: f32 = -9.0;
+// Marks an empty cell of the decoration layer
+const EMPTY_CELL: char = '.';
+
+
+// A tile map, written as rows of characters that the legend turns into textures
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct ArenaLayout {
+    pub name: String,
+    // Size of a tile in the world
+    pub tile_size: f32,
+    // Textures each character can be drawn with, relative to `textures/rpg/`.
+    // When there are several, every cell picks one of them.
+    pub legend: HashMap<char, Vec<String>>,
+    // Textures drawn around the ground
+    pub wall: Vec<String>,
+    // Rows of the ground from top to bottom, all of the same length
+    pub ground: Vec<String>,
+    // Drawn over the ground, the same size as it, or left out
+    #[serde(default)]
+    pub decorations: Vec<String>,
+    // Props each character of the obstacle layer stands for
+    #[serde(default)]
+    pub props: HashMap<char, PropKind>,
+    // Props in the way of the player and enemies, the same size as the ground, or left out
+    #[serde(default)]
+    pub obstacles: Vec<String>,
+}
+
+impl ArenaLayout {
+    fn columns(&self) -> usize {
+        self.ground.first().map_or(0, |row| row.chars().count())
+    }
+
+    fn rows(&self) -> usize {
+        self.ground.len()
+    }
+
+    // The ground is centered on the origin
+    pub fn bounds(&self) -> ArenaBounds {
+        let half_width = self.columns() as f32 * self.tile_size / 2.0;
+        let half_height = self.rows() as f32 * self.tile_size / 2.0;
+        ArenaBounds {
+            left: -half_width,
+            right: half_width,
+            bottom: -half_height,
+            top: half_height,
+        }
+    }
+
+    // Center of a cell, counting from the top left of the ground. The walls are at -1 and one past the end.
+    fn cell_position(&self, column: isize, row: isize) -> Vec2 {
+        let bounds = self.bounds();
+        Vec2::new(
+            bounds.left + (column as f32 + 0.5) * self.tile_size,
+            bounds.top - (row as f32 + 0.5) * self.tile_size,
+        )
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let columns = self.columns();
+        if columns == 0 {
+            return Err("the ground is empty".to_string());
+        }
+        if self.wall.is_empty() {
+            return Err("there is no wall texture".to_string());
+        }
+        let in_legend = |cell: &char| self.legend.contains_key(cell);
+        let in_props = |cell: &char| self.props.contains_key(cell);
+        let layers: [(&str, &Vec<String>, &dyn Fn(&char) -> bool); 3] = [
+            ("ground", &self.ground, &in_legend),
+            ("decorations", &self.decorations, &in_legend),
+            ("obstacles", &self.obstacles, &in_props),
+        ];
+        for (name, rows, known) in layers {
+            if name != "ground" && rows.is_empty() {
+                continue;
+            }
+            if rows.len() != self.rows() {
+                return Err(format!("{} has {} rows instead of {}", name, rows.len(), self.rows()));
+            }
+            for (index, row) in rows.iter().enumerate() {
+                if row.chars().count() != columns {
+                    return Err(format!("row {} of {} is not {} tiles long", index, name, columns));
+                }
+                if let Some(cell) = row.chars().find(|cell| *cell != EMPTY_CELL && !known(cell)) {
+                    return Err(format!("{:?} in row {} of {} is not defined", cell, index, name));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// The playable area, the player can't leave it and enemies spawn inside it
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct ArenaBounds {
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32,
+}
+
+// Used until the layout of the run is loaded
+impl Default for ArenaBounds {
+    fn default() -> Self {
+        ArenaBounds {
+            left: LEFT_WALL,
+            right: RIGHT_WALL,
+            bottom: BOTTOM_WALL,
+            top: TOP_WALL,
+        }
+    }
+}
+
+impl ArenaBounds {
+    pub fn min(&self) -> Vec2 {
+        Vec2::new(self.left, self.bottom)
+    }
+
+    pub fn max(&self) -> Vec2 {
+        Vec2::new(self.right, self.top)
+    }
+
+    pub fn contains(&self, position: Vec2) -> bool {
+        position.cmpge(self.min()).all() && position.cmple(self.max()).all()
+    }
+
+    pub fn clamp(&self, position: Vec2) -> Vec2 {
+        position.clamp(self.min(), self.max())
+    }
+
+    // The bounds moved inwards by `margin` on every side
+    pub fn shrink(&self, margin: f32) -> Self {
+        ArenaBounds {
+            left: self.left + margin,
+            right: self.right - margin,
+            bottom: self.bottom + margin,
+            top: self.top - margin,
+        }
+    }
+
+    pub fn grow(&self, margin: f32) -> Self {
+        self.shrink(-margin)
+    }
+}
+
+// Everything that is drawn, the walls included. Nothing outside of it should ever be on screen.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct ArenaExtent(pub ArenaBounds);
+
+impl Default for ArenaExtent {
+    fn default() -> Self {
+        ArenaExtent(ArenaBounds::default().grow(DEFAULT_WALL_THICKNESS))
+    }
+}
+
+#[derive(Resource)]
+struct ArenaLayouts(Vec<Handle<ArenaLayout>>);
+
+// The layout of the current run, and whether it has been drawn yet
+#[derive(Resource)]
+pub struct CurrentArena {
+    pub layout: Handle<ArenaLayout>,
+    spawned: bool,
+}
+
+// Tag component used to tag the tiles of the arena
+#[derive(Component)]
+pub struct OnArena;
+
+
+#[derive(Default)]
+pub struct ArenaLayoutLoader;
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum ArenaLayoutLoaderError {
+    #[error("Could not load arena layout: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse arena layout: {0}")]
+    RonSpannedError(#[from] ron::error::SpannedError),
+    #[error("Invalid arena layout: {0}")]
+    Layout(String),
+}
+
+impl AssetLoader for ArenaLayoutLoader {
+    type Asset = ArenaLayout;
+    type Settings = ();
+    type Error = ArenaLayoutLoaderError;
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let layout = ron::de::from_bytes::<ArenaLayout>(&bytes)?;
+            layout.validate().map_err(ArenaLayoutLoaderError::Layout)?;
+            Ok(layout)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["arena.ron"]
+    }
+}
+
+
+fn load_arena_layouts(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ArenaLayouts(
+        ARENA_PATHS.iter().map(|path| asset_server.load(*path)).collect(),
+    ));
+}
+
+fn pick_arena(mut commands: Commands, layouts: Res<ArenaLayouts>, mut rng: ResMut<GameRng>) {
+    let index = rng.gen_range(0..layouts.0.len());
+    commands.insert_resource(CurrentArena {
+        layout: layouts.0[index].clone(),
+        spawned: false,
+    });
+}
+
+fn reset_arena(mut commands: Commands, mut bounds: ResMut<ArenaBounds>, mut extent: ResMut<ArenaExtent>) {
+    commands.remove_resource::<CurrentArena>();
+    *bounds = ArenaBounds::default();
+    *extent = ArenaExtent::default();
+}
+
+// Picks one of the textures of a cell. Hashing the cell instead of drawing from the game's
+// random numbers keeps a layout looking the same every time, whatever the seed.
+fn cell_texture(textures: &[String], column: isize, row: isize) -> &str {
+    let hash = (column.wrapping_mul(73_856_093) ^ row.wrapping_mul(19_349_663)).unsigned_abs();
+    &textures[hash % textures.len()]
+}
+
+fn spawn_arena(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    layouts: Res<Assets<ArenaLayout>>,
+    mut current: ResMut<CurrentArena>,
+    mut bounds: ResMut<ArenaBounds>,
+    mut extent: ResMut<ArenaExtent>,
+) {
+    if current.spawned {
+        return;
+    }
+    let Some(layout) = layouts.get(&current.layout) else {
+        return;
+    };
+    current.spawned = true;
+    *bounds = layout.bounds();
+    // One ring of wall tiles
+    *extent = ArenaExtent(layout.bounds().grow(layout.tile_size));
+    info!("Playing in the {} arena", layout.name);
+
+    let mut spawn_tile = |texture: &str, position: Vec2, layer: f32, size: Option<Vec2>| {
+        commands.spawn((SpriteBundle {
+            texture: asset_server.load(format!("textures/rpg/{}", texture)),
+            sprite: Sprite {
+                custom_size: size,
+                ..default()
+            },
+            transform: Transform {
+                translation: position.extend(layer),
+                scale: if size.is_some() { Vec3::ONE } else { Vec3::splat(layout.tile_size / SOURCE_TILE_SIZE) },
+                ..default()
+            },
+            ..default()
+        }, OnArena));
+    };
+
+    let tile = Some(Vec2::splat(layout.tile_size));
+    let columns = layout.columns() as isize;
+    let rows = layout.rows() as isize;
+    for row in -1..=rows {
+        for column in -1..=columns {
+            let position = layout.cell_position(column, row);
+            if row < 0 || row == rows || column < 0 || column == columns {
+                spawn_tile(cell_texture(&layout.wall, column, row), position, GROUND_LAYER, tile);
+                continue;
+            }
+
+            let ground = layout.ground[row as usize].chars().nth(column as usize).unwrap_or(EMPTY_CELL);
+            if let Some(textures) = layout.legend.get(&ground) {
+                spawn_tile(cell_texture(textures, column, row), position, GROUND_LAYER, tile);
+            }
+            let decoration = layout.decorations
+                .get(row as usize)
+                .and_then(|cells| cells.chars().nth(column as usize))
+                .unwrap_or(EMPTY_CELL);
+            if let Some(textures) = layout.legend.get(&decoration) {
+                // Decorations keep their own size instead of filling the tile
+                spawn_tile(cell_texture(textures, column, row), position, DECORATION_LAYER, None);
+            }
+        }
+    }
+
+    for (row, cells) in layout.obstacles.iter().enumerate() {
+        for (column, cell) in cells.chars().enumerate() {
+            if let Some(kind) = layout.props.get(&cell) {
+                let position = layout.cell_position(column as isize, row as isize);
+                let prop = obstacle::spawn_prop(&mut commands, &asset_server, *kind, position);
+                commands.entity(prop).insert(OnArena);
+            }
+        }
+    }
+}
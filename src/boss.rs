@@ -0,0 +1,388 @@
+use std::f32::consts::TAU;
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use crate::{
+    arena::ArenaBounds,
+    camera::CameraShake,
+    despawn_with_component,
+    enemy::{self, Charger, ContactCooldown, EnemyAssets, EnemyKilled, EnemyKind, EnemyModifiers},
+    health::{self, DamageEvent, DamageKind, Health},
+    objective::RunProgress,
+    player::{self, Player},
+    wave::{WaveSchedule, WaveScheduleHandle, WaveTimer},
+    GameState, TEXT_COLOR,
+};
+
+// This plugin runs the boss encounter: the queen bee shows up at the start of every wave
+// flagged with `boss: true` in the wave schedule and goes through harsher attack phases
+// as its health drops
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<BossSpawnTracker>()
+        .add_systems(Startup, load_boss_projectile_assets)
+        .add_systems(OnExit(GameState::Menu), reset_boss_spawn_tracker)
+        .add_systems(OnEnter(GameState::Menu), (
+            despawn_with_component::<BossProjectile>,
+            despawn_with_component::<OnBossHealthBar>,
+        ))
+        .add_systems(FixedUpdate, (
+            spawn_scheduled_boss,
+            update_boss_phase,
+            boss_attack,
+            move_boss_projectiles,
+            boss_projectile_damage_player,
+            count_boss_defeats,
+        ).chain().after(enemy::despawn_dead_enemies).before(health::apply_damage).run_if(in_state(GameState::Game)))
+        .add_systems(Update, update_boss_health_bar.run_if(in_state(GameState::Game)));
+    }
+}
+
+const BOSS_CONTACT_COOLDOWN_SECONDS: f32 = 1.0;
+const BOSS_SUMMON_INTERVAL_SECONDS: f32 = 4.0;
+const BOSS_SUMMON_COUNT: u32 = 6;
+const BOSS_SUMMON_RADIUS: f32 = 80.0;
+const BOSS_BARRAGE_INTERVAL_SECONDS: f32 = 1.5;
+const BOSS_PROJECTILE_COUNT: u32 = 12;
+const BOSS_PROJECTILE_SPEED: f32 = 250.0;
+const BOSS_PROJECTILE_SIZE: f32 = 6.0;
+const BOSS_PROJECTILE_DAMAGE: u32 = 5;
+const BOSS_PROJECTILE_COLOR: Color = Color::rgb(6.0, 5.0, 0.5);
+// How much every summon and barrage shakes the camera
+const BOSS_SLAM_TRAUMA: f32 = 0.5;
+const BOSS_HEALTH_BAR_COLOR: Color = Color::rgb(0.8, 0.65, 0.1);
+const BOSS_HEALTH_BAR_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BossPhase {
+    // Above two thirds of its health: dashes at the player
+    Charge,
+    // Above one third: calls in minions
+    Summon,
+    // Below that: fires rings of stingers
+    Barrage,
+}
+
+impl BossPhase {
+    fn from_health(health: &Health) -> Self {
+        let fraction = health.fraction();
+        if fraction > 2.0 / 3.0 {
+            BossPhase::Charge
+        } else if fraction > 1.0 / 3.0 {
+            BossPhase::Summon
+        } else {
+            BossPhase::Barrage
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Boss {
+    pub phase: BossPhase,
+    attack: Timer,
+    volleys: u32,
+}
+
+#[derive(Component)]
+pub struct BossProjectile {
+    velocity: Vec2,
+    // The boss that fired it
+    source: Entity,
+}
+
+// Mesh and material shared by every boss projectile, made once at startup
+#[derive(Resource)]
+struct BossProjectileAssets {
+    mesh: Mesh2dHandle,
+    material: Handle<ColorMaterial>,
+}
+
+// Remembers the last wave that was checked for a boss, so each boss wave spawns exactly one
+#[derive(Resource, Default)]
+struct BossSpawnTracker {
+    last_wave: Option<u32>
+}
+
+// Tag component used to tag entities added for the boss health bar
+#[derive(Component)]
+struct OnBossHealthBar;
+
+#[derive(Component)]
+struct BossHealthBarFill;
+
+
+fn reset_boss_spawn_tracker(mut tracker: ResMut<BossSpawnTracker>) {
+    *tracker = BossSpawnTracker::default();
+}
+
+fn spawn_scheduled_boss(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    enemy_assets: Res<EnemyAssets>,
+    schedule_handle: Res<WaveScheduleHandle>,
+    schedules: Res<Assets<WaveSchedule>>,
+    wave_timer: Res<WaveTimer>,
+    mut tracker: ResMut<BossSpawnTracker>,
+    bounds: Res<ArenaBounds>,
+    player_query: Query<&Transform, With<Player>>,
+    health_bar_query: Query<(), With<OnBossHealthBar>>,
+) {
+    if tracker.last_wave == Some(wave_timer.waves) {
+        return;
+    }
+    let Some(schedule) = schedules.get(&schedule_handle.0) else {
+        return;
+    };
+    tracker.last_wave = Some(wave_timer.waves);
+
+    // Boss waves only count the first time through, not when the last wave repeats
+    let Some(wave) = schedule.waves.get(wave_timer.waves as usize) else {
+        return;
+    };
+    if !wave.boss {
+        return;
+    }
+
+    // Come in from the side of the arena opposite the player
+    let player_position = player_query.single().translation.truncate();
+    let side = if player_position.x > 0.0 { bounds.left } else { bounds.right };
+    let position = Vec2::new(side * 0.8, 0.0);
+
+    // Scaled by its wave like everything else the wave brings in
+    let modifiers = EnemyModifiers {
+        speed: wave.speed_multiplier,
+        health: wave.health_multiplier,
+        ..default()
+    };
+    let boss = enemy::spawn_enemy(&mut commands, &enemy_assets,
+        EnemyKind::BossBee, position, modifiers);
+    commands.entity(boss).insert((
+        Boss {
+            phase: BossPhase::Charge,
+            attack: Timer::from_seconds(BOSS_SUMMON_INTERVAL_SECONDS, TimerMode::Repeating),
+            volleys: 0,
+        },
+        Charger::default(),
+        ContactCooldown(Timer::from_seconds(BOSS_CONTACT_COOLDOWN_SECONDS, TimerMode::Once)),
+    ));
+
+    if health_bar_query.is_empty() {
+        boss_health_bar_setup(&mut commands, &asset_server);
+    }
+}
+
+fn update_boss_phase(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Boss, &Health)>,
+) {
+    for (boss_entity, mut boss, health) in query.iter_mut() {
+        let phase = BossPhase::from_health(health);
+        if phase == boss.phase {
+            continue;
+        }
+        boss.phase = phase;
+        match phase {
+            BossPhase::Charge => {
+                commands.entity(boss_entity).insert(Charger::default());
+            }
+            BossPhase::Summon => {
+                commands.entity(boss_entity).remove::<Charger>();
+                boss.attack = Timer::from_seconds(BOSS_SUMMON_INTERVAL_SECONDS, TimerMode::Repeating);
+            }
+            BossPhase::Barrage => {
+                commands.entity(boss_entity).remove::<Charger>();
+                boss.attack = Timer::from_seconds(BOSS_BARRAGE_INTERVAL_SECONDS, TimerMode::Repeating);
+            }
+        }
+    }
+}
+
+fn load_boss_projectile_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(BossProjectileAssets {
+        mesh: meshes.add(shape::Circle::new(BOSS_PROJECTILE_SIZE).into()).into(),
+        material: materials.add(ColorMaterial::from(BOSS_PROJECTILE_COLOR)),
+    });
+}
+
+fn boss_attack(
+    mut commands: Commands,
+    time: Res<Time>,
+    enemy_assets: Res<EnemyAssets>,
+    projectile_assets: Res<BossProjectileAssets>,
+    bounds: Res<ArenaBounds>,
+    mut shake_events: EventWriter<CameraShake>,
+    mut query: Query<(Entity, &mut Boss, &Transform)>,
+) {
+    for (boss_entity, mut boss, boss_transform) in query.iter_mut() {
+        if boss.phase == BossPhase::Charge || !boss.attack.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let boss_position = boss_transform.translation.truncate();
+        shake_events.send(CameraShake { trauma: BOSS_SLAM_TRAUMA });
+
+        match boss.phase {
+            BossPhase::Charge => {}
+            BossPhase::Summon => {
+                for i in 0..BOSS_SUMMON_COUNT {
+                    let angle = TAU * i as f32 / BOSS_SUMMON_COUNT as f32;
+                    let position = bounds.clamp(boss_position + Vec2::from_angle(angle) * BOSS_SUMMON_RADIUS);
+                    enemy::spawn_enemy(&mut commands, &enemy_assets,
+                        EnemyKind::Kobold, position, EnemyModifiers::default());
+                }
+            }
+            BossPhase::Barrage => {
+                // Offset every other volley so standing in a gap only works once
+                let offset = if boss.volleys % 2 == 0 { 0.0 } else { 0.5 };
+                boss.volleys += 1;
+                for i in 0..BOSS_PROJECTILE_COUNT {
+                    let angle = TAU * (i as f32 + offset) / BOSS_PROJECTILE_COUNT as f32;
+                    commands.spawn((MaterialMesh2dBundle {
+                        mesh: projectile_assets.mesh.clone(),
+                        material: projectile_assets.material.clone(),
+                        transform: Transform::from_translation(boss_position.extend(1.)),
+                        ..default()
+                        },
+                        BossProjectile { velocity: Vec2::from_angle(angle) * BOSS_PROJECTILE_SPEED, source: boss_entity },
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn move_boss_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    bounds: Res<ArenaBounds>,
+    mut query: Query<(Entity, &mut Transform, &BossProjectile)>,
+) {
+    for (projectile_entity, mut projectile_transform, projectile) in query.iter_mut() {
+        projectile_transform.translation += (projectile.velocity * time.delta_seconds()).extend(0.);
+
+        if !bounds.contains(projectile_transform.translation.truncate()) {
+            commands.entity(projectile_entity).despawn();
+        }
+    }
+}
+
+fn boss_projectile_damage_player(
+    mut commands: Commands,
+    mut damage_events: EventWriter<DamageEvent>,
+    player_query: Query<(Entity, &Transform), With<Player>>,
+    projectile_query: Query<(Entity, &Transform, &BossProjectile)>,
+    boss_query: Query<(), With<Boss>>,
+) {
+    let (player_entity, player_transform) = player_query.single();
+    for (projectile_entity, projectile_transform, projectile) in projectile_query.iter() {
+        let distance = projectile_transform.translation.truncate().distance(player_transform.translation.truncate());
+        if distance < player::PLAYER_SIZE / 2.0 + BOSS_PROJECTILE_SIZE {
+            commands.entity(projectile_entity).despawn();
+            damage_events.send(DamageEvent {
+                // The boss may have died since it fired
+                source: boss_query.contains(projectile.source).then_some(projectile.source),
+                target: player_entity,
+                amount: BOSS_PROJECTILE_DAMAGE,
+                kind: DamageKind::Projectile,
+            });
+        }
+    }
+}
+
+fn count_boss_defeats(
+    mut commands: Commands,
+    mut killed_events: EventReader<EnemyKilled>,
+    mut progress: ResMut<RunProgress>,
+    boss_query: Query<(), With<Boss>>,
+    health_bar_query: Query<Entity, With<OnBossHealthBar>>,
+) {
+    for killed in killed_events.read() {
+        if killed.kind == EnemyKind::BossBee {
+            progress.bosses_defeated += 1;
+        }
+    }
+
+    if boss_query.is_empty() {
+        for entity in health_bar_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn boss_health_bar_setup(commands: &mut Commands, asset_server: &AssetServer) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(20.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnBossHealthBar,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Queen Bee",
+                TextStyle {
+                    font,
+                    font_size: 30.0,
+                    color: TEXT_COLOR,
+                },
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(60.0),
+                        height: Val::Px(20.0),
+                        ..default()
+                    },
+                    background_color: BOSS_HEALTH_BAR_BACKGROUND.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(100.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: BOSS_HEALTH_BAR_COLOR.into(),
+                            ..default()
+                        },
+                        BossHealthBarFill,
+                    ));
+                });
+        });
+}
+
+fn update_boss_health_bar(
+    boss_query: Query<&Health, With<Boss>>,
+    mut fill_query: Query<&mut Style, With<BossHealthBarFill>>,
+) {
+    // With several bosses alive at once the bar follows the healthiest one
+    let Some(fraction) = boss_query
+        .iter()
+        .map(Health::fraction)
+        .reduce(f32::max) else {
+        return;
+    };
+    for mut style in fill_query.iter_mut() {
+        style.width = Val::Percent(fraction * 100.0);
+    }
+}
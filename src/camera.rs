@@ -0,0 +1,184 @@
+use bevy::{input::mouse::MouseWheel, prelude::*};
+use rand::Rng;
+use crate::{
+    arena::ArenaExtent,
+    health::Damaged,
+    player::Player,
+    GameState,
+};
+
+// This plugin moves the camera: it follows the player once they leave a dead zone in the middle
+// of the screen, never shows anything past the arena walls, zooms with the mouse wheel and shakes.
+// Shaking is driven by trauma: anything can add some with a `CameraShake` event, the shake grows
+// with the square of it and it wears off on its own.
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_event::<CameraShake>()
+        .init_resource::<CameraSettings>()
+        .add_systems(OnEnter(GameState::Menu), reset_camera)
+        .add_systems(Update, (
+            (zoom_camera, shake_on_player_hit).run_if(in_state(GameState::Game)),
+            add_trauma,
+            move_camera,
+        ).chain());
+    }
+}
+
+const PLAYER_HIT_TRAUMA: f32 = 0.35;
+// Fraction of the wanted zoom one mouse wheel notch changes it by
+const ZOOM_STEP: f32 = 0.1;
+
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct CameraSettings {
+    // Half the size of the box around the camera the player can move in without it following
+    pub dead_zone: Vec2,
+    // How quickly the camera catches up, higher is snappier
+    pub follow_speed: f32,
+    // World units per screen pixel, smaller is closer. Never further out than the arena fits the screen.
+    pub zoom: f32,
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    // Furthest the camera moves at full trauma
+    pub max_shake_offset: f32,
+    // Trauma lost per second
+    pub trauma_decay: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings {
+            dead_zone: Vec2::new(60.0, 40.0),
+            follow_speed: 6.0,
+            zoom: 0.7,
+            min_zoom: 0.4,
+            max_zoom: 1.0,
+            max_shake_offset: 16.0,
+            trauma_decay: 1.5,
+        }
+    }
+}
+
+#[derive(Component, Default)]
+pub struct CameraController {
+    // Where the camera looks before shaking
+    focus: Vec2,
+    // From 0 to 1
+    trauma: f32,
+}
+
+// Send this event to shake the camera, `trauma` adds up to at most 1
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CameraShake {
+    pub trauma: f32,
+}
+
+
+fn reset_camera(mut query: Query<(&mut CameraController, &mut Transform)>) {
+    for (mut controller, mut transform) in query.iter_mut() {
+        *controller = CameraController::default();
+        *transform = Transform::from_translation(Vec3::new(0.0, 0.0, transform.translation.z));
+    }
+}
+
+fn zoom_camera(mut wheel_events: EventReader<MouseWheel>, mut settings: ResMut<CameraSettings>) {
+    let notches: f32 = wheel_events.read().map(|event| event.y.signum()).sum();
+    if notches == 0.0 {
+        return;
+    }
+    // Scrolling up zooms in
+    let zoom = settings.zoom * (1.0 - ZOOM_STEP).powf(notches);
+    settings.zoom = zoom.clamp(settings.min_zoom, settings.max_zoom);
+}
+
+fn shake_on_player_hit(
+    mut damaged_events: EventReader<Damaged>,
+    player_query: Query<(), With<Player>>,
+    mut shake_events: EventWriter<CameraShake>,
+) {
+    if damaged_events.read().any(|damaged| player_query.contains(damaged.entity)) {
+        shake_events.send(CameraShake { trauma: PLAYER_HIT_TRAUMA });
+    }
+}
+
+fn add_trauma(mut shake_events: EventReader<CameraShake>, mut query: Query<&mut CameraController>) {
+    let trauma: f32 = shake_events.read().map(|shake| shake.trauma).sum();
+    if trauma <= 0.0 {
+        return;
+    }
+    for mut controller in query.iter_mut() {
+        controller.trauma = (controller.trauma + trauma).min(1.0);
+    }
+}
+
+fn move_camera(
+    time: Res<Time>,
+    settings: Res<CameraSettings>,
+    extent: Res<ArenaExtent>,
+    game_state: Res<State<GameState>>,
+    player_query: Query<&Transform, (With<Player>, Without<CameraController>)>,
+    mut camera_query: Query<(&mut CameraController, &mut Transform, &mut OrthographicProjection)>,
+) {
+    let Ok((mut controller, mut transform, mut projection)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    // The menus are drawn over an empty world, leave the camera where `reset_camera` put it
+    if *game_state.get() == GameState::Menu || *game_state.get() == GameState::Splash {
+        return;
+    }
+
+    // Zoom in far enough that the arena covers the whole screen
+    let extent = extent.0;
+    let arena_size = extent.max() - extent.min();
+    let unscaled_view = projection.area.size() / projection.scale;
+    let mut scale = settings.zoom;
+    if unscaled_view.x > 0.0 && unscaled_view.y > 0.0 {
+        scale = scale.min((arena_size / unscaled_view).min_element());
+    }
+    if projection.scale != scale {
+        projection.scale = scale;
+    }
+    let half_view = unscaled_view * scale / 2.0;
+
+    // Only gameplay moves the camera, it holds still on the pause and level up screens
+    if *game_state.get() == GameState::Game {
+        if let Ok(player_transform) = player_query.get_single() {
+            let player_position = player_transform.translation.truncate();
+            let offset = player_position - controller.focus;
+            let outside = offset - offset.clamp(-settings.dead_zone, settings.dead_zone);
+            let target = controller.focus + outside;
+            let smoothing = 1.0 - (-settings.follow_speed * time.delta_seconds()).exp();
+            controller.focus = controller.focus.lerp(target, smoothing);
+        }
+    }
+    controller.focus = clamp_view(controller.focus, half_view, extent.min(), extent.max());
+
+    controller.trauma = (controller.trauma - settings.trauma_decay * time.delta_seconds()).max(0.0);
+    let shake = controller.trauma * controller.trauma;
+    // Shaking is cosmetic, it doesn't draw from the seeded gameplay rng
+    let mut rng = rand::thread_rng();
+    let offset = if shake > 0.0 {
+        Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)) * settings.max_shake_offset * shake
+    } else {
+        Vec2::ZERO
+    };
+
+    // Still never past the walls, even mid shake
+    let position = clamp_view(controller.focus + offset, half_view, extent.min(), extent.max());
+    transform.translation = position.extend(transform.translation.z);
+}
+
+// Keeps a view of `half_view` around `position` between `min` and `max`, centered if it doesn't fit
+fn clamp_view(position: Vec2, half_view: Vec2, min: Vec2, max: Vec2) -> Vec2 {
+    let low = min + half_view;
+    let high = max - half_view;
+    let center = (min + max) / 2.0;
+    Vec2::new(
+        if low.x <= high.x { position.x.clamp(low.x, high.x) } else { center.x },
+        if low.y <= high.y { position.y.clamp(low.y, high.y) } else { center.y },
+    )
+}
@@ -1,119 +1,519 @@
-use std::time::Duration;
-use bevy::{
-    core_pipeline::{
-        bloom::BloomSettings,
-        tonemapping::Tonemapping,
-    },
-    prelude::*,
-    sprite::MaterialMesh2dBundle,
+use bevy::{prelude::*, utils::HashMap};
+use serde::Deserialize;
+use crate::{
+    animation::{AnimationClips, Clip, DespawnWhenFinished, SpriteAnimation},
+    health::{self, DamageEvent, DamageKind, Health, Invulnerability},
+    obstacle::{self, ColliderQuery},
+    player::{self, Player},
+    spatial::SpatialGrid,
+    steering::{self, Neighbor, SteeringWeights},
+    GameState,
 };
-use rand::Rng;
-use crate::{game::{LEFT_WALL, RIGHT_WALL, TOP_WALL, BOTTOM_WALL}, GameState, player, health::Health};
-use super::player::Player;
 
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app
-        .insert_resource(WaveTimer {
-            timer: Timer::new(Duration::from_secs(ENEMY_SPAWN_INTERVAL_SECONDS as u64), TimerMode::Repeating),
-        })
+        .add_event::<EnemyKilled>()
+        .add_systems(Startup, load_enemy_assets)
         .add_systems(FixedUpdate, (
-            move_enemy, 
-            spawn_enemy,
+            despawn_dead_enemies,
+            burrow_enemies,
+            move_enemy,
             enemy_damage_player
-        ).chain().run_if(in_state(GameState::Game)));
+        ).chain().before(health::apply_damage).run_if(in_state(GameState::Game)));
     }
 }
 
-const ENEMY_SIZE: f32 = 10.0;
-const ENEMY_SPEED: f32 = 0.5;
-const ENEMY_COLOR: Color = Color::rgb(10.7, 0.3, 0.3);
-const ENEMY_SPAWN_PER_INTERVAL: u32 = 25;
-const ENEMY_MIN_SPAWN_DISTANCE: f32 = 100.0;
-pub const ENEMY_SPAWN_INTERVAL_SECONDS: u32 = 1;
 pub const ENEMY_DAMAGE: u32 = 10;
+// Sprites are tiny pixel art, draw them bigger than their source frames
+const ENEMY_SPRITE_SCALE: f32 = 2.0;
+// Every kind is drawn at its own depth, so sprites sharing a texture sort next to each other
+// and are drawn in one batch
+const ENEMY_KIND_LAYER_STEP: f32 = 0.01;
+const CHARGE_RANGE: f32 = 250.0;
+const CHARGE_SPEED_MULTIPLIER: f32 = 3.5;
+const CHARGE_COOLDOWN_SECONDS: f32 = 3.0;
+const CHARGE_DURATION_SECONDS: f32 = 0.4;
+const BURROW_SECONDS: f32 = 2.0;
+const SURFACE_SECONDS: f32 = 3.0;
+const BURROWED_SPEED_MULTIPLIER: f32 = 2.0;
+const SPLIT_COUNT: u32 = 2;
+const SPLIT_SIZE_MULTIPLIER: f32 = 0.6;
+const SPLIT_HEALTH_MULTIPLIER: f32 = 0.4;
 
 
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EnemyKind {
+    Kobold,
+    BlueSlime,
+    GreenSlime,
+    OrangeSlime,
+    Fox,
+    Worm,
+    // Only spawned by the boss encounter, see `boss::BossPlugin`. Wave schedules listing it fail to load.
+    BossBee,
+}
+
+impl EnemyKind {
+    pub const ALL: [EnemyKind; 7] = [
+        EnemyKind::Kobold,
+        EnemyKind::BlueSlime,
+        EnemyKind::GreenSlime,
+        EnemyKind::OrangeSlime,
+        EnemyKind::Fox,
+        EnemyKind::Worm,
+        EnemyKind::BossBee,
+    ];
+
+    fn layer(self) -> f32 {
+        self as usize as f32 * ENEMY_KIND_LAYER_STEP
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnemyBehavior {
+    // Walks straight at the player
+    Chase,
+    // Walks at the player and dashes at them once close enough
+    Charge,
+    // Walks at the player and breaks into smaller copies when killed
+    Split,
+    // Alternates between surfacing and burrowing, untouchable but faster while underground
+    Burrow,
+}
+
+// Everything that makes an enemy type play differently
+pub struct EnemyArchetype {
+    pub texture: &'static str,
+    pub frame_size: Vec2,
+    pub frames: usize,
+    pub clips: AnimationClips,
+    // Collision radius
+    pub size: f32,
+    pub speed: f32,
+    pub hit_points: u32,
+    pub damage: u32,
+    // Experience dropped when killed
+    pub xp: u32,
+    pub behavior: EnemyBehavior,
+    // How it moves among other enemies, see `steering::steer`
+    pub steering: SteeringWeights,
+}
+
+impl EnemyKind {
+    pub fn archetype(self) -> EnemyArchetype {
+        match self {
+            EnemyKind::Kobold => EnemyArchetype {
+                texture: "textures/rpg/mobs/kobold-idle.png",
+                frame_size: Vec2::new(24.0, 24.0),
+                frames: 15,
+                clips: AnimationClips::idle_run(Clip::new(0, 14, 10.0), Clip::new(0, 14, 15.0)),
+                size: 10.0,
+                speed: 110.0,
+                hit_points: 100,
+                damage: ENEMY_DAMAGE,
+                xp: 1,
+                behavior: EnemyBehavior::Chase,
+                steering: SteeringWeights { seek: 1.0, separation: 1.5, alignment: 0.3, cohesion: 0.0 },
+            },
+            EnemyKind::BlueSlime => EnemyArchetype {
+                texture: "textures/rpg/mobs/slime-blue.png",
+                frame_size: Vec2::new(16.0, 24.0),
+                frames: 4,
+                clips: AnimationClips::idle_run(Clip::new(0, 3, 6.0), Clip::new(0, 3, 8.0)),
+                size: 12.0,
+                speed: 55.0,
+                hit_points: 200,
+                damage: ENEMY_DAMAGE,
+                xp: 3,
+                behavior: EnemyBehavior::Split,
+                steering: SteeringWeights { seek: 1.0, separation: 1.2, alignment: 0.1, cohesion: 0.2 },
+            },
+            EnemyKind::GreenSlime => EnemyArchetype {
+                texture: "textures/rpg/mobs/slime-green.png",
+                frame_size: Vec2::new(16.0, 24.0),
+                frames: 4,
+                clips: AnimationClips::idle_run(Clip::new(0, 3, 6.0), Clip::new(0, 3, 8.0)),
+                size: 12.0,
+                speed: 65.0,
+                hit_points: 160,
+                damage: ENEMY_DAMAGE - 2,
+                xp: 2,
+                behavior: EnemyBehavior::Split,
+                steering: SteeringWeights { seek: 1.0, separation: 1.2, alignment: 0.1, cohesion: 0.2 },
+            },
+            EnemyKind::OrangeSlime => EnemyArchetype {
+                texture: "textures/rpg/mobs/slime-orange.png",
+                frame_size: Vec2::new(16.0, 24.0),
+                frames: 4,
+                clips: AnimationClips::idle_run(Clip::new(0, 3, 6.0), Clip::new(0, 3, 8.0)),
+                size: 14.0,
+                speed: 45.0,
+                hit_points: 300,
+                damage: ENEMY_DAMAGE + 5,
+                xp: 4,
+                behavior: EnemyBehavior::Split,
+                steering: SteeringWeights { seek: 1.0, separation: 1.2, alignment: 0.1, cohesion: 0.2 },
+            },
+            EnemyKind::Fox => EnemyArchetype {
+                texture: "textures/rpg/mobs/fox-run.png",
+                frame_size: Vec2::new(24.0, 24.0),
+                frames: 6,
+                clips: AnimationClips::idle_run(Clip::new(0, 0, 1.0), Clip::new(0, 5, 12.0)),
+                size: 10.0,
+                speed: 140.0,
+                hit_points: 70,
+                damage: ENEMY_DAMAGE + 2,
+                xp: 2,
+                behavior: EnemyBehavior::Charge,
+                steering: SteeringWeights { seek: 1.0, separation: 1.0, alignment: 0.6, cohesion: 0.1 },
+            },
+            EnemyKind::Worm => EnemyArchetype {
+                texture: "textures/rpg/mobs/worm-run-idle.png",
+                frame_size: Vec2::new(16.0, 24.0),
+                frames: 31,
+                clips: AnimationClips::idle_run(Clip::new(9, 26, 10.0), Clip::new(0, 8, 10.0)),
+                size: 8.0,
+                speed: 80.0,
+                hit_points: 90,
+                damage: ENEMY_DAMAGE,
+                xp: 2,
+                behavior: EnemyBehavior::Burrow,
+                steering: SteeringWeights { seek: 1.0, separation: 0.8, alignment: 0.0, cohesion: 0.0 },
+            },
+            EnemyKind::BossBee => EnemyArchetype {
+                texture: "textures/rpg/mobs/boss_bee.png",
+                frame_size: Vec2::new(34.0, 34.0),
+                frames: 1,
+                clips: AnimationClips::idle_run(Clip::new(0, 0, 1.0), Clip::new(0, 0, 1.0)),
+                size: 30.0,
+                speed: 60.0,
+                hit_points: 4000,
+                damage: ENEMY_DAMAGE * 2,
+                xp: 50,
+                behavior: EnemyBehavior::Chase,
+                // Shoves through its own minions, they make way for it
+                steering: SteeringWeights { seek: 1.0, separation: 0.0, alignment: 0.0, cohesion: 0.0 },
+            },
+        }
+    }
+}
+
+// Scales applied on top of an archetype, e.g. by the current wave
+#[derive(Clone, Copy, Debug)]
+pub struct EnemyModifiers {
+    pub speed: f32,
+    pub health: f32,
+    pub size: f32,
+}
+
+impl Default for EnemyModifiers {
+    fn default() -> Self {
+        EnemyModifiers { speed: 1.0, health: 1.0, size: 1.0 }
+    }
+}
+
+#[derive(Component)]
+pub struct Enemy {
+    pub kind: EnemyKind,
+    pub size: f32,
+    pub speed: f32,
+    pub damage: u32,
+    // Multipliers the enemy spawned with, passed on to anything it splits into
+    pub speed_multiplier: f32,
+    pub health_multiplier: f32,
+    pub steering: SteeringWeights,
+    // Where it headed last tick, per second
+    pub velocity: Vec2,
+}
+
+#[derive(Component)]
+pub struct Charger {
+    cooldown: Timer,
+    dash: Timer,
+    direction: Vec2,
+}
+
+impl Default for Charger {
+    fn default() -> Self {
+        let mut dash = Timer::from_seconds(CHARGE_DURATION_SECONDS, TimerMode::Once);
+        // Start out walking, not dashing
+        dash.tick(dash.duration());
+        Charger {
+            cooldown: Timer::from_seconds(CHARGE_COOLDOWN_SECONDS, TimerMode::Once),
+            dash,
+            direction: Vec2::ZERO,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Splits;
+
 #[derive(Component)]
-struct Enemy;
+pub struct Burrower {
+    timer: Timer,
+}
 
+// Marks burrowed enemies, which can't hurt or be hurt
+#[derive(Component)]
+pub struct Burrowed;
+
+// Enemies with this survive touching the player and can hit again once the timer is up
+#[derive(Component)]
+pub struct ContactCooldown(pub Timer);
 
+// Texture atlases shared by every enemy of a kind, made once at startup
 #[derive(Resource)]
-pub struct WaveTimer {
-    pub timer: Timer
+pub struct EnemyAssets {
+    atlases: HashMap<EnemyKind, Handle<TextureAtlas>>,
+}
+
+impl EnemyAssets {
+    pub fn atlas(&self, kind: EnemyKind) -> Handle<TextureAtlas> {
+        self.atlases[&kind].clone()
+    }
+}
+
+#[derive(Event)]
+pub struct EnemyKilled {
+    pub kind: EnemyKind,
+    pub position: Vec2,
 }
 
-fn move_enemy(
-    time: Res<Time>, 
+
+fn load_enemy_assets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>) {
+        let atlases = EnemyKind::ALL.into_iter().map(|kind| {
+            let archetype = kind.archetype();
+            let texture_atlas = TextureAtlas::from_grid(
+                asset_server.load(archetype.texture),
+                archetype.frame_size,
+                archetype.frames,
+                1,
+                None,
+                None,
+            );
+            (kind, texture_atlases.add(texture_atlas))
+        }).collect();
+        commands.insert_resource(EnemyAssets { atlases });
+}
+
+pub fn move_enemy(
+    time: Res<Time>,
+    grid: Res<SpatialGrid>,
     mut query: ParamSet<(
         Query<&Transform, With<Player>>,
-        Query<&mut Transform, With<Enemy>>)>) {
-    
-        
-        let player_position= query.p0().single().translation.clone();
-        let distance_moved = ENEMY_SPEED * time.delta_seconds();
-
-        for mut enemy_transform in query.p1().iter_mut() {
-            enemy_transform.translation.x +=
-                    distance_moved * (player_position.x - enemy_transform.translation.x);
-            enemy_transform.translation.y +=
-                    distance_moved * (player_position.y - enemy_transform.translation.y);
+        Query<(Entity, &mut Transform, &mut Enemy, &mut SpriteAnimation, Option<&mut Charger>, Option<&Burrowed>)>)>,
+    obstacle_query: ColliderQuery) {
+    let player_position = query.p0().single().translation.truncate();
+    let colliders = obstacle::colliders(&obstacle_query);
+    // Where everyone is before anyone moves this tick, burrowed enemies are out of the way.
+    // The grid is from the end of the last tick, it only narrows down who is close by.
+    let neighbors: HashMap<Entity, Neighbor> = query.p1().iter()
+        .filter(|(_, _, _, _, _, burrowed)| burrowed.is_none())
+        .map(|(entity, transform, enemy, _, _, _)| (entity, Neighbor {
+            position: transform.translation.truncate(),
+            velocity: enemy.velocity,
+            size: enemy.size,
+        }))
+        .collect();
+
+    for (enemy_entity, mut enemy_transform, mut enemy, mut animation, charger, burrowed) in query.p1().iter_mut() {
+        let enemy_position = enemy_transform.translation.truncate();
+        let to_player = player_position - enemy_position;
+        let mut direction = to_player.normalize_or_zero();
+        // Burrowed enemies go under obstacles and everyone else
+        if burrowed.is_none() {
+            let search_distance = steering::neighbor_distance(enemy.size, grid.max_radius());
+            let others = grid.within(enemy_position, search_distance)
+                .filter(|entry| entry.entity != enemy_entity)
+                .filter_map(|entry| neighbors.get(&entry.entity).copied());
+            direction = steering::steer(enemy_entity.index(), enemy_position, enemy.size, player_position, enemy.steering, others);
+            let length = direction.length();
+            direction = obstacle::avoid_obstacles(enemy_position, player_position, direction.normalize_or_zero(), enemy.size, &colliders) * length;
+        }
+        let mut velocity = direction * enemy.speed;
+
+        if burrowed.is_some() {
+            velocity *= BURROWED_SPEED_MULTIPLIER;
+        }
+
+        if let Some(mut charger) = charger {
+            if !charger.dash.finished() {
+                charger.dash.tick(time.delta());
+                velocity = charger.direction * enemy.speed * CHARGE_SPEED_MULTIPLIER;
+            } else if charger.cooldown.tick(time.delta()).finished() && to_player.length() < CHARGE_RANGE {
+                charger.cooldown.reset();
+                charger.dash.reset();
+                charger.direction = to_player.normalize_or_zero();
+            }
+        }
+        enemy.velocity = velocity;
+
+        // Never overshoot the player
+        let step = velocity * time.delta_seconds();
+        let step = step.clamp_length_max(to_player.length());
+        let mut new_position = enemy_position + step;
+        if burrowed.is_none() {
+            new_position = obstacle::resolve_collisions(new_position, enemy.size, &colliders);
         }
+        enemy_transform.translation = new_position.extend(enemy_transform.translation.z);
+        animation.set_movement(new_position - enemy_position);
+    }
 }
 
+fn burrow_enemies(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Burrower, &mut Visibility, Option<&Burrowed>)>) {
+        for (enemy_entity, mut burrower, mut visibility, burrowed) in query.iter_mut() {
+            if !burrower.timer.tick(time.delta()).just_finished() {
+                continue;
+            }
+            if burrowed.is_some() {
+                commands.entity(enemy_entity).remove::<Burrowed>();
+                *visibility = Visibility::Inherited;
+                burrower.timer = Timer::from_seconds(SURFACE_SECONDS, TimerMode::Once);
+            } else {
+                commands.entity(enemy_entity).insert(Burrowed);
+                *visibility = Visibility::Hidden;
+                burrower.timer = Timer::from_seconds(BURROW_SECONDS, TimerMode::Once);
+            }
+        }
+}
 
-fn enemy_damage_player(
+pub fn despawn_dead_enemies(
     mut commands: Commands,
-    mut query: ParamSet<(
-        Query<(&Transform, &mut Health, With<Player>)>,
-        Query<(Entity, &Transform, With<Enemy>)>)>) {
-        let player_position = query.p0().single().0.translation.clone();
-
-        let mut player_hits = 0;
-        for (enemy_entity, enemy_transform, _) in query.p1().iter_mut() {
-            let distance = enemy_transform.translation.distance(player_position);
-            if distance < player::PLAYER_SIZE {
-                commands.entity(enemy_entity).despawn();
-                player_hits += 1;
+    enemy_assets: Res<EnemyAssets>,
+    mut killed_events: EventWriter<EnemyKilled>,
+    query: Query<(Entity, &Transform, &Enemy, &Health, &Handle<TextureAtlas>, &TextureAtlasSprite, &SpriteAnimation, Option<&Splits>)>) {
+        for (enemy_entity, enemy_transform, enemy, health, texture_atlas, sprite, animation, splits) in query.iter() {
+            if health.hit_points > 0 {
+                continue;
+            }
+            commands.entity(enemy_entity).despawn_recursive();
+            // Leave the body behind for its death animation
+            commands.spawn((SpriteSheetBundle {
+                texture_atlas: texture_atlas.clone(),
+                sprite: sprite.clone(),
+                transform: *enemy_transform,
+                ..default()
+                },
+                animation.dying(),
+                DespawnWhenFinished,
+            ));
+            let position = enemy_transform.translation.truncate();
+            killed_events.send(EnemyKilled { kind: enemy.kind, position });
+
+            if splits.is_some() {
+                let modifiers = EnemyModifiers {
+                    speed: enemy.speed_multiplier,
+                    health: enemy.health_multiplier * SPLIT_HEALTH_MULTIPLIER,
+                    size: SPLIT_SIZE_MULTIPLIER,
+                };
+                for i in 0..SPLIT_COUNT {
+                    // Spread the pieces sideways so they don't stack
+                    let offset = Vec2::new(enemy.size * (i as f32 - 0.5) * 2.0, 0.);
+                    let piece = spawn_enemy(&mut commands, &enemy_assets,
+                        enemy.kind, position + offset, modifiers);
+                    commands.entity(piece).remove::<Splits>();
+                }
             }
         }
-        query.p0().single_mut().1.hit_points -= player_hits * ENEMY_DAMAGE;
 }
 
-fn spawn_enemy(mut commands: Commands, 
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+pub fn enemy_damage_player(
+    mut commands: Commands,
     time: Res<Time>,
-    mut wave_timer: ResMut<WaveTimer>,
-    mut query: ParamSet<(
-        Query<&Transform, With<Player>>,
-        Query<&mut Transform, With<Enemy>>)>
-    ) {
-        wave_timer.timer.tick(time.delta());
-
-        let player_position= query.p0().single().translation.clone();
-
-        if wave_timer.timer.finished() {
-            let mut rng = rand::thread_rng();
-
-            for _ in 0..ENEMY_SPAWN_PER_INTERVAL {
-                let x = rng.gen_range(LEFT_WALL..RIGHT_WALL);
-                let y = rng.gen_range(BOTTOM_WALL..TOP_WALL);
-                let distance = Vec2::new(x, y).distance(player_position.truncate());
-                if distance > ENEMY_MIN_SPAWN_DISTANCE {
-                    commands.spawn((MaterialMesh2dBundle {
-                        mesh: meshes.add(shape::Circle::new(ENEMY_SIZE).into()).into(),
-                        material: materials.add(ColorMaterial::from(ENEMY_COLOR)),
-                        transform: Transform::from_translation(Vec3::new(x, y, 0.)),
-                        ..default()
-                        }, 
-                        Enemy,
-                        Health { hit_points: 100}
-                    ));
+    grid: Res<SpatialGrid>,
+    mut damage_events: EventWriter<DamageEvent>,
+    player_query: Query<(Entity, &Transform, Option<&Invulnerability>), With<Player>>,
+    enemy_query: Query<&Enemy, Without<Burrowed>>,
+    mut cooldown_query: Query<&mut ContactCooldown, Without<Burrowed>>) {
+        for mut cooldown in cooldown_query.iter_mut() {
+            cooldown.0.tick(time.delta());
+        }
+
+        let (player_entity, player_transform, invulnerability) = player_query.single();
+        // Nothing gets through the i-frames, so no enemy spends its hit on them
+        if invulnerability.is_some_and(Invulnerability::is_active) {
+            return;
+        }
+        let player_position = player_transform.translation.truncate();
+
+        for entry in grid.within(player_position, player::PLAYER_SIZE / 2.0) {
+            let Ok(enemy) = enemy_query.get(entry.entity) else {
+                continue;
+            };
+            if let Ok(mut cooldown) = cooldown_query.get_mut(entry.entity) {
+                if !cooldown.0.finished() {
+                    continue;
                 }
+                cooldown.0.reset();
+            } else {
+                commands.entity(entry.entity).despawn_recursive();
+            }
+            damage_events.send(DamageEvent {
+                source: Some(entry.entity),
+                target: player_entity,
+                amount: enemy.damage,
+                kind: DamageKind::Contact,
+            });
+            // The first hit starts the i-frames, everyone else keeps theirs for later
+            if invulnerability.is_some() {
+                break;
             }
         }
 }
 
+pub fn spawn_enemy(commands: &mut Commands,
+    enemy_assets: &EnemyAssets,
+    kind: EnemyKind,
+    position: Vec2,
+    modifiers: EnemyModifiers) -> Entity {
+        let archetype = kind.archetype();
+        let mut enemy = commands.spawn((SpriteSheetBundle {
+            texture_atlas: enemy_assets.atlas(kind),
+            sprite: TextureAtlasSprite {
+                custom_size: Some(archetype.frame_size * ENEMY_SPRITE_SCALE * modifiers.size),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(kind.layer())),
+            ..default()
+            },
+            Enemy {
+                kind,
+                size: archetype.size * modifiers.size,
+                speed: archetype.speed * modifiers.speed,
+                damage: archetype.damage,
+                speed_multiplier: modifiers.speed,
+                health_multiplier: modifiers.health,
+                steering: archetype.steering,
+                velocity: Vec2::ZERO,
+            },
+            Health::new(((archetype.hit_points as f32 * modifiers.health).round() as u32).max(1)),
+            SpriteAnimation::new(archetype.clips),
+        ));
+
+        match archetype.behavior {
+            EnemyBehavior::Chase => {}
+            EnemyBehavior::Charge => {
+                enemy.insert(Charger::default());
+            }
+            EnemyBehavior::Split => {
+                enemy.insert(Splits);
+            }
+            EnemyBehavior::Burrow => {
+                enemy.insert(Burrower {
+                    timer: Timer::from_seconds(SURFACE_SECONDS, TimerMode::Once),
+                });
+            }
+        }
+
+        enemy.id()
+}
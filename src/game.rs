@@ -6,12 +6,13 @@ use bevy::{
     text::{BreakLineOn, Text2dBounds},
 };
 
-use crate::{player::PlayerPlugin, health::HealthPlugin, enemy::EnemyPlugin, GameState};
+use crate::{animation::SpriteAnimationPlugin, arena::ArenaPlugin, boss::BossPlugin, camera::{CameraController, CameraPlugin}, player::PlayerPlugin, health::HealthPlugin, hud::HudPlugin, enemy::EnemyPlugin, light::LightPlugin, loot::LootPlugin, objective::ObjectivePlugin, particles::ParticlePlugin, progression::ProgressionPlugin, quality::QualityPlugin, rng::{GameSeed, RngPlugin}, spatial::SpatialPlugin, wave::WavePlugin, GameState, KeyBindings};
 
-pub const LEFT_WALL: f32 = -450.;
-pub const RIGHT_WALL: f32 = 450.;
-pub const BOTTOM_WALL: f32 = -300.;
-pub const TOP_WALL: f32 = 300.;
+// Bounds of the arena before its layout is loaded, the size of the layouts in `assets/arenas/`
+pub const LEFT_WALL: f32 = -448.;
+pub const RIGHT_WALL: f32 = 448.;
+pub const BOTTOM_WALL: f32 = -304.;
+pub const TOP_WALL: f32 = 304.;
 
 
 pub struct GamePlugin;
@@ -20,10 +21,29 @@ pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((
+            ArenaPlugin,
+            BossPlugin,
             EnemyPlugin,
             HealthPlugin,
-            PlayerPlugin
+            LightPlugin,
+            LootPlugin,
+            ObjectivePlugin,
+            PlayerPlugin,
+            ProgressionPlugin,
+            RngPlugin,
+            SpatialPlugin,
+            WavePlugin
         ))
+        // What the run looks like rather than how it plays
+        .add_plugins((
+            CameraPlugin,
+            HudPlugin,
+            ParticlePlugin,
+            QualityPlugin,
+            SpriteAnimationPlugin
+        ))
+        .add_state::<GameState>()
+        .init_resource::<KeyBindings>()
         .add_systems(OnEnter(GameState::GameWon), end_game)
         .add_systems(OnEnter(GameState::GameLost), end_game)
         .add_systems(FixedUpdate, listen_for_restart.run_if(in_state(GameState::GameLost)))
@@ -35,7 +55,9 @@ struct EndGameText;
 
 fn end_game(mut commands: Commands, 
     asset_server: Res<AssetServer>, 
-    game_state: Res<State<GameState>>
+    game_state: Res<State<GameState>>,
+    seed: Res<GameSeed>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<CameraController>>
  )
     {
     let font = asset_server.load("fonts/FiraSans-Bold.ttf");
@@ -46,8 +68,8 @@ fn end_game(mut commands: Commands,
     };
 
     let text = match **game_state {
-        GameState::GameWon => format!("You win! \nPress any key to return to the menu."),
-        GameState::GameLost => "Game Over! You lost.\nPress any key to return to the menu.".to_string(),
+        GameState::GameWon => format!("You win! \nPress any key to return to the menu.\nSeed {}", seed.current),
+        GameState::GameLost => format!("Game Over! You lost.\nPress any key to return to the menu.\nSeed {}", seed.current),
         _ => "unreachable".to_string()
     };
 
@@ -58,7 +80,11 @@ fn end_game(mut commands: Commands,
     };
 
     let box_size = Vec2::new(600.0, 600.0);
-    let box_position = Vec2::new(0.0, 0.0);
+    // In front of the camera, the same size on screen however far it is zoomed in
+    let (box_position, box_scale) = camera_query
+        .get_single()
+        .map(|(transform, projection)| (transform.translation.truncate(), projection.scale))
+        .unwrap_or((Vec2::ZERO, 1.0));
     commands
         .spawn((SpriteBundle {
             sprite: Sprite {
@@ -66,7 +92,7 @@ fn end_game(mut commands: Commands,
                 custom_size: Some(Vec2::new(box_size.x, box_size.y)),
                 ..default()
             },
-            transform: Transform::from_translation(box_position.extend(6.0)),
+            transform: Transform::from_translation(box_position.extend(6.0)).with_scale(Vec3::splat(box_scale)),
             ..default()
         }, EndGameText))
         .with_children(|builder| {
@@ -1,22 +1,31 @@
-use std::time::Duration;
-use bevy::{
-    core_pipeline::{
-        bloom::BloomSettings,
-        tonemapping::Tonemapping,
-    },
-    prelude::*,
-    sprite::MaterialMesh2dBundle,
-};
-use rand::Rng;
-use crate::{game::{LEFT_WALL, RIGHT_WALL, TOP_WALL, BOTTOM_WALL}, GameState};
+use bevy::prelude::*;
+use crate::GameState;
 use super::player::Player;
 
+// Every bit of damage in the game is a `DamageEvent`, applied in one place by `apply_damage`:
+// a `Shield` soaks it up first, hit points never go below zero, entities with `Invulnerability`
+// shrug off hits for a moment after taking one, every hit that lands sends a `Damaged` event
+// and whatever runs out of hit points sends a `Died` event. Healing works the same way with
+// `HealEvent`, and hit points also regenerate.
+// Systems sending them run `.before(health::apply_healing)` or `.before(health::apply_damage)`, so
+// they land the same tick.
 pub struct HealthPlugin;
 
 impl Plugin for HealthPlugin {
     fn build(&self, app: &mut App) {
         app
-        .add_systems(FixedUpdate, check_player_loss.run_if(in_state(GameState::Game)));
+        .add_event::<DamageEvent>()
+        .add_event::<HealEvent>()
+        .add_event::<Damaged>()
+        .add_event::<Died>()
+        .add_systems(FixedUpdate, (
+            tick_invulnerability,
+            regenerate,
+            recharge_shields,
+            apply_healing,
+            apply_damage,
+            check_player_loss,
+        ).chain().run_if(in_state(GameState::Game)));
     }
 }
 
@@ -24,15 +33,233 @@ impl Plugin for HealthPlugin {
 
 #[derive(Component)]
 pub struct Health {
-    pub hit_points: u32
+    pub hit_points: u32,
+    pub max_hit_points: u32,
+    // Hit points regained per second
+    pub regeneration: f32,
+    // Regeneration that doesn't add up to a whole hit point yet
+    regeneration_carry: f32,
 }
 
+impl Health {
+    // At full health, not regenerating
+    pub fn new(max_hit_points: u32) -> Self {
+        Health {
+            hit_points: max_hit_points,
+            max_hit_points,
+            regeneration: 0.0,
+            regeneration_carry: 0.0,
+        }
+    }
+
+    // Heals up to the maximum and returns what was left over
+    pub fn heal(&mut self, amount: u32) -> u32 {
+        let healed = amount.min(self.max_hit_points.saturating_sub(self.hit_points));
+        self.hit_points += healed;
+        amount - healed
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.max_hit_points == 0 {
+            return 0.0;
+        }
+        (self.hit_points as f32 / self.max_hit_points as f32).clamp(0.0, 1.0)
+    }
+}
+
+// Extra hit points on top of `Health` that take damage first. After a while without getting hit
+// they start coming back.
+#[derive(Component)]
+pub struct Shield {
+    pub points: u32,
+    pub max_points: u32,
+    // Points regained per second while recharging
+    pub recharge_rate: f32,
+    // Time after the last hit before recharging starts
+    recharge_delay: Timer,
+    recharge_carry: f32,
+}
 
+impl Shield {
+    // Starts out full
+    pub fn new(max_points: u32, recharge_rate: f32, recharge_delay_seconds: f32) -> Self {
+        let mut recharge_delay = Timer::from_seconds(recharge_delay_seconds, TimerMode::Once);
+        recharge_delay.tick(recharge_delay.duration());
+        Shield {
+            points: max_points,
+            max_points,
+            recharge_rate,
+            recharge_delay,
+            recharge_carry: 0.0,
+        }
+    }
 
-fn check_player_loss(query: Query<&Health, With<Player>>,
+    // Soaks up as much of the damage as it can and returns the rest
+    fn absorb(&mut self, amount: u32) -> u32 {
+        self.recharge_delay.reset();
+        self.recharge_carry = 0.0;
+        let absorbed = amount.min(self.points);
+        self.points -= absorbed;
+        amount - absorbed
+    }
+
+    fn charge(&mut self, amount: u32) {
+        self.points = (self.points + amount).min(self.max_points);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageKind {
+    // Touching an enemy
+    Contact,
+    // The player's light aura
+    Light,
+    // Shot by the boss
+    Projectile,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DamageEvent {
+    // What dealt the damage, if it is still around
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub amount: u32,
+    pub kind: DamageKind,
+}
+
+// Healing past the maximum hit points tops up the target's shield, if it has one
+#[derive(Event, Debug, Clone, Copy)]
+pub struct HealEvent {
+    pub target: Entity,
+    pub amount: u32,
+}
+
+// A `DamageEvent` that got through invulnerability, even if the shield took all of it.
+// `amount` is before the shield, so a hit looks the same whether or not it was soaked up.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Damaged {
+    pub entity: Entity,
+    pub source: Option<Entity>,
+    pub amount: u32,
+    pub kind: DamageKind,
+}
+
+// Sent once, the tick an entity's hit points reach zero
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Died {
+    pub entity: Entity,
+    pub source: Option<Entity>,
+    pub kind: DamageKind,
+}
+
+// Ignores damage for `seconds` after every hit it takes
+#[derive(Component)]
+pub struct Invulnerability {
+    timer: Timer,
+}
+
+impl Invulnerability {
+    pub fn new(seconds: f32) -> Self {
+        let mut timer = Timer::from_seconds(seconds, TimerMode::Once);
+        // Not invulnerable until the first hit
+        timer.tick(timer.duration());
+        Invulnerability { timer }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.timer.finished()
+    }
+}
+
+
+fn tick_invulnerability(time: Res<Time>, mut query: Query<&mut Invulnerability>) {
+    for mut invulnerability in query.iter_mut() {
+        invulnerability.timer.tick(time.delta());
+    }
+}
+
+fn regenerate(time: Res<Time>, mut query: Query<&mut Health>) {
+    for mut health in query.iter_mut() {
+        if health.regeneration <= 0.0 || health.hit_points == 0 {
+            continue;
+        }
+        health.regeneration_carry += health.regeneration * time.delta_seconds();
+        let regenerated = health.regeneration_carry.floor();
+        health.regeneration_carry -= regenerated;
+        health.heal(regenerated as u32);
+    }
+}
+
+fn recharge_shields(time: Res<Time>, mut query: Query<&mut Shield>) {
+    for mut shield in query.iter_mut() {
+        if !shield.recharge_delay.tick(time.delta()).finished() || shield.points >= shield.max_points {
+            continue;
+        }
+        shield.recharge_carry += shield.recharge_rate * time.delta_seconds();
+        let recharged = shield.recharge_carry.floor();
+        shield.recharge_carry -= recharged;
+        shield.charge(recharged as u32);
+    }
+}
+
+pub fn apply_healing(
+    mut heal_events: EventReader<HealEvent>,
+    mut query: Query<(&mut Health, Option<&mut Shield>)>,
+) {
+    for heal in heal_events.read() {
+        let Ok((mut health, shield)) = query.get_mut(heal.target) else {
+            continue;
+        };
+        // Nothing brings the dead back
+        if health.hit_points == 0 {
+            continue;
+        }
+        let overheal = health.heal(heal.amount);
+        if let Some(mut shield) = shield {
+            shield.charge(overheal);
+        }
+    }
+}
+
+pub fn apply_damage(
+    mut damage_events: EventReader<DamageEvent>,
+    mut damaged_events: EventWriter<Damaged>,
+    mut died_events: EventWriter<Died>,
+    mut query: Query<(&mut Health, Option<&mut Invulnerability>, Option<&mut Shield>)>,
+) {
+    for damage in damage_events.read() {
+        let Ok((mut health, invulnerability, shield)) = query.get_mut(damage.target) else {
+            continue;
+        };
+        // Already dead, it only dies once
+        if health.hit_points == 0 {
+            continue;
+        }
+        if let Some(mut invulnerability) = invulnerability {
+            if invulnerability.is_active() {
+                continue;
+            }
+            invulnerability.timer.reset();
+        }
+        damaged_events.send(Damaged { entity: damage.target, source: damage.source, amount: damage.amount, kind: damage.kind });
+
+        let amount = match shield {
+            Some(mut shield) => shield.absorb(damage.amount),
+            None => damage.amount,
+        };
+        health.hit_points = health.hit_points.saturating_sub(amount);
+        if health.hit_points == 0 {
+            died_events.send(Died { entity: damage.target, source: damage.source, kind: damage.kind });
+        }
+    }
+}
+
+fn check_player_loss(
+    mut died_events: EventReader<Died>,
+    player_query: Query<(), With<Player>>,
     mut game_state: ResMut<NextState<GameState>>
 ) {
-    if query.single().hit_points <= 0 {
+    if died_events.read().any(|died| player_query.contains(died.entity)) {
         game_state.set(GameState::GameLost);
     }
-}
\ No newline at end of file
+}
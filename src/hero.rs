@@ -0,0 +1,193 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    animation::{AnimationClips, Clip},
+    health::Health,
+    light::{LightAura, LIGHT_AURA_RADIUS},
+    player::{PlayerStats, PLAYER_INITIAL_HIT_POINTS, PLAYER_SPEED},
+};
+
+// The playable characters, picked on the character select screen before each run.
+// Each one starts with its own stats and one ability nobody else has.
+
+const REGENERATION_ABILITY: f32 = 1.0;
+const MAGNET_ABILITY: f32 = 2.0;
+const SEARING_LIGHT_ABILITY: u32 = 15;
+const QUICK_PULSE_ABILITY: f32 = 0.7;
+
+
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hero {
+    #[default]
+    Gabe,
+    HatGuy,
+    Mani,
+    Sensei,
+}
+
+// The hero the next run starts with
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SelectedHero(pub Hero);
+
+// Texture atlas of every hero, made once at startup and shared by the select screen and the player
+#[derive(Resource)]
+pub struct HeroAssets {
+    atlases: HashMap<Hero, Handle<TextureAtlas>>,
+}
+
+impl HeroAssets {
+    pub fn atlas(&self, hero: Hero) -> Handle<TextureAtlas> {
+        self.atlases[&hero].clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeroAbility {
+    // Regenerates hit points from the start
+    SecondWind,
+    // Pulls experience gems in from further away
+    Magnet,
+    // The light hurts more
+    SearingLight,
+    // The light pulses more often
+    QuickPulse,
+}
+
+impl HeroAbility {
+    pub fn name(self) -> &'static str {
+        match self {
+            HeroAbility::SecondWind => "Second Wind",
+            HeroAbility::Magnet => "Magnet",
+            HeroAbility::SearingLight => "Searing Light",
+            HeroAbility::QuickPulse => "Quick Pulse",
+        }
+    }
+
+    pub fn description(self) -> String {
+        match self {
+            HeroAbility::SecondWind => format!("Regenerate {} HP/s", REGENERATION_ABILITY),
+            HeroAbility::Magnet => format!("Pickup radius x{}", MAGNET_ABILITY),
+            HeroAbility::SearingLight => format!("Light damage +{}", SEARING_LIGHT_ABILITY),
+            HeroAbility::QuickPulse => format!("Light pulses {:.0}% faster", (1.0 / QUICK_PULSE_ABILITY - 1.0) * 100.0),
+        }
+    }
+
+    fn apply(self, stats: &mut PlayerStats, health: &mut Health, aura: &mut LightAura) {
+        match self {
+            HeroAbility::SecondWind => health.regeneration += REGENERATION_ABILITY,
+            HeroAbility::Magnet => stats.pickup_radius *= MAGNET_ABILITY,
+            HeroAbility::SearingLight => aura.damage += SEARING_LIGHT_ABILITY,
+            HeroAbility::QuickPulse => {
+                let duration = aura.pulse.duration().mul_f32(QUICK_PULSE_ABILITY);
+                aura.pulse.set_duration(duration);
+            }
+        }
+    }
+}
+
+pub struct HeroDefinition {
+    pub name: &'static str,
+    pub texture: &'static str,
+    pub frame_size: Vec2,
+    pub frames: usize,
+    pub clips: AnimationClips,
+    pub speed: f32,
+    pub max_hit_points: u32,
+    pub light_radius: f32,
+    pub ability: HeroAbility,
+}
+
+impl Hero {
+    pub const ALL: [Hero; 4] = [Hero::Gabe, Hero::HatGuy, Hero::Mani, Hero::Sensei];
+
+    pub fn definition(self) -> HeroDefinition {
+        match self {
+            // All rounder
+            Hero::Gabe => HeroDefinition {
+                name: "Gabe",
+                texture: "textures/rpg/chars/gabe/gabe-idle-run.png",
+                frame_size: Vec2::new(24.0, 24.0),
+                frames: 7,
+                clips: AnimationClips::idle_run(Clip::new(0, 0, 1.0), Clip::new(1, 6, 12.0)),
+                speed: PLAYER_SPEED,
+                max_hit_points: PLAYER_INITIAL_HIT_POINTS,
+                light_radius: LIGHT_AURA_RADIUS,
+                ability: HeroAbility::SecondWind,
+            },
+            // Slow and sturdy
+            Hero::HatGuy => HeroDefinition {
+                name: "Hat Guy",
+                texture: "textures/rpg/chars/hat-guy/hat-guy.png",
+                frame_size: Vec2::new(16.0, 22.0),
+                frames: 1,
+                clips: AnimationClips::idle_run(Clip::new(0, 0, 1.0), Clip::new(0, 0, 1.0)),
+                speed: PLAYER_SPEED * 0.85,
+                max_hit_points: PLAYER_INITIAL_HIT_POINTS + 50,
+                light_radius: LIGHT_AURA_RADIUS,
+                ability: HeroAbility::Magnet,
+            },
+            // Fast and fragile
+            Hero::Mani => HeroDefinition {
+                name: "Mani",
+                texture: "textures/rpg/chars/mani/mani-idle-run.png",
+                frame_size: Vec2::new(24.0, 24.0),
+                frames: 7,
+                clips: AnimationClips::idle_run(Clip::new(0, 0, 1.0), Clip::new(1, 6, 12.0)),
+                speed: PLAYER_SPEED * 1.15,
+                max_hit_points: PLAYER_INITIAL_HIT_POINTS - 30,
+                light_radius: LIGHT_AURA_RADIUS * 0.9,
+                ability: HeroAbility::SearingLight,
+            },
+            // Keeps enemies at a distance
+            Hero::Sensei => HeroDefinition {
+                name: "Sensei",
+                texture: "textures/rpg/chars/sensei/sensei.png",
+                frame_size: Vec2::new(16.0, 23.0),
+                frames: 1,
+                clips: AnimationClips::idle_run(Clip::new(0, 0, 1.0), Clip::new(0, 0, 1.0)),
+                speed: PLAYER_SPEED * 0.95,
+                max_hit_points: PLAYER_INITIAL_HIT_POINTS - 10,
+                light_radius: LIGHT_AURA_RADIUS * 1.3,
+                ability: HeroAbility::QuickPulse,
+            },
+        }
+    }
+}
+
+impl HeroDefinition {
+    fn texture_atlas(&self, asset_server: &AssetServer) -> TextureAtlas {
+        TextureAtlas::from_grid(
+            asset_server.load(self.texture),
+            self.frame_size,
+            self.frames,
+            1,
+            None,
+            None,
+        )
+    }
+
+    // Stats, health and light the hero starts a run with, ability included
+    pub fn starting_stats(&self) -> (PlayerStats, Health, LightAura) {
+        let mut stats = PlayerStats {
+            speed: self.speed,
+            ..default()
+        };
+        let mut health = Health::new(self.max_hit_points);
+        let mut aura = LightAura {
+            radius: self.light_radius,
+            ..default()
+        };
+        self.ability.apply(&mut stats, &mut health, &mut aura);
+        (stats, health, aura)
+    }
+}
+
+pub fn load_hero_assets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>) {
+    let atlases = Hero::ALL.into_iter().map(|hero| {
+        (hero, texture_atlases.add(hero.definition().texture_atlas(&asset_server)))
+    }).collect();
+    commands.insert_resource(HeroAssets { atlases });
+}
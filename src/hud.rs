@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+use crate::{
+    despawn_with_component,
+    health::{Health, Shield},
+    objective::RunProgress,
+    player::Player,
+    progression::PlayerLevel,
+    wave::WaveTimer,
+    GameState, TEXT_COLOR,
+};
+
+// This plugin shows the player's health and how the run is going while playing.
+// Like the menu screens it only lives as long as its state, so it is hidden on the pause,
+// level up and end screens.
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(OnEnter(GameState::Game), hud_setup)
+        .add_systems(OnExit(GameState::Game), despawn_with_component::<OnHud>)
+        .add_systems(Update, (update_health_bar, update_hud_stats).run_if(in_state(GameState::Game)));
+    }
+}
+
+const HEALTH_BAR_WIDTH: f32 = 200.0;
+const HEALTH_BAR_COLOR: Color = Color::rgb(0.75, 0.1, 0.1);
+const HEALTH_BAR_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+
+
+// Tag component used to tag entities added for the HUD
+#[derive(Component)]
+struct OnHud;
+
+#[derive(Component)]
+struct HealthBarFill;
+
+#[derive(Component)]
+struct HealthBarText;
+
+// Which number a HUD line shows
+#[derive(Component, Clone, Copy)]
+enum HudStat {
+    Time,
+    Wave,
+    Kills,
+    Gold,
+    Level,
+}
+
+impl HudStat {
+    fn text(self, progress: &RunProgress, wave_timer: &WaveTimer, player_level: &PlayerLevel) -> String {
+        match self {
+            HudStat::Time => {
+                let seconds = progress.elapsed.as_secs();
+                format!("Time {}:{:02}", seconds / 60, seconds % 60)
+            }
+            HudStat::Wave => format!("Wave {}", wave_timer.waves + 1),
+            HudStat::Kills => format!("Kills {}", progress.kills),
+            HudStat::Gold => format!("Gold {}", progress.gold),
+            HudStat::Level => format!("Level {}", player_level.level),
+        }
+    }
+}
+
+fn health_text(health: &Health, shield: &Shield) -> String {
+    if shield.points == 0 {
+        return format!("{} / {}", health.hit_points, health.max_hit_points);
+    }
+    format!("{} / {} +{}", health.hit_points, health.max_hit_points, shield.points)
+}
+
+fn hud_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    progress: Res<RunProgress>,
+    wave_timer: Res<WaveTimer>,
+    player_level: Res<PlayerLevel>,
+    player_query: Query<(&Health, &Shield), With<Player>>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: 24.0,
+        color: TEXT_COLOR,
+    };
+    let (health, shield) = player_query.single();
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(16.0),
+                    left: Val::Px(10.0),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+            OnHud,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(HEALTH_BAR_WIDTH),
+                        height: Val::Px(24.0),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::bottom(Val::Px(5.0)),
+                        ..default()
+                    },
+                    background_color: HEALTH_BAR_BACKGROUND.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(health.fraction() * 100.0),
+                                height: Val::Percent(100.0),
+                                position_type: PositionType::Absolute,
+                                left: Val::Px(0.0),
+                                ..default()
+                            },
+                            background_color: HEALTH_BAR_COLOR.into(),
+                            ..default()
+                        },
+                        HealthBarFill,
+                    ));
+                    parent.spawn((
+                        TextBundle::from_section(health_text(health, shield), text_style.clone()),
+                        HealthBarText,
+                    ));
+                });
+
+            for stat in [HudStat::Time, HudStat::Wave, HudStat::Kills, HudStat::Gold, HudStat::Level] {
+                parent.spawn((
+                    TextBundle::from_section(
+                        stat.text(&progress, &wave_timer, &player_level),
+                        text_style.clone(),
+                    ),
+                    stat,
+                ));
+            }
+        });
+}
+
+fn update_health_bar(
+    player_query: Query<(&Health, &Shield), (With<Player>, Or<(Changed<Health>, Changed<Shield>)>)>,
+    mut fill_query: Query<&mut Style, With<HealthBarFill>>,
+    mut text_query: Query<&mut Text, With<HealthBarText>>,
+) {
+    let Ok((health, shield)) = player_query.get_single() else {
+        return;
+    };
+    for mut style in fill_query.iter_mut() {
+        style.width = Val::Percent(health.fraction() * 100.0);
+    }
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = health_text(health, shield);
+    }
+}
+
+fn update_hud_stats(
+    progress: Res<RunProgress>,
+    wave_timer: Res<WaveTimer>,
+    player_level: Res<PlayerLevel>,
+    mut query: Query<(&mut Text, &HudStat)>,
+) {
+    for (mut text, stat) in query.iter_mut() {
+        let value = stat.text(&progress, &wave_timer, &player_level);
+        // Only touch the text when the number changed, so it isn't laid out again every frame
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
@@ -0,0 +1,103 @@
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+
+pub mod animation;
+pub mod arena;
+pub mod boss;
+pub mod camera;
+pub mod enemy;
+pub mod health;
+pub mod hero;
+pub mod hud;
+pub mod light;
+pub mod loot;
+pub mod objective;
+pub mod obstacle;
+pub mod particles;
+pub mod pause;
+pub mod persistence;
+pub mod player;
+pub mod progression;
+pub mod quality;
+pub mod rng;
+pub mod settings;
+pub mod simulation;
+pub mod sound;
+pub mod spatial;
+pub mod splash;
+pub mod steering;
+pub mod menu;
+pub mod game;
+pub mod wave;
+
+
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
+pub enum GameState {
+    #[default]
+    Splash,
+    Menu,
+    Game,
+    Pause,
+    LevelUp,
+    GameLost,
+    GameWon
+}
+
+  
+#[derive(Resource, Debug, Component, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum DisplayQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+#[derive(Resource, Debug, Component, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct Volume(pub u32);
+
+impl Default for Volume {
+    fn default() -> Self {
+        Volume(7)
+    }
+}
+
+// Keys for every action, any key of the list triggers it
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub move_up: Vec<KeyCode>,
+    pub move_down: Vec<KeyCode>,
+    pub move_left: Vec<KeyCode>,
+    pub move_right: Vec<KeyCode>,
+    pub pause: Vec<KeyCode>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            move_up: vec![KeyCode::W],
+            move_down: vec![KeyCode::S],
+            move_left: vec![KeyCode::A],
+            move_right: vec![KeyCode::D],
+            pause: vec![KeyCode::Escape, KeyCode::P],
+        }
+    }
+}
+
+
+pub const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+
+// Generic system that takes a component as a parameter, and will despawn all entities with that component
+pub fn despawn_with_component<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
+    for entity in &to_despawn {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn despawn_all(to_despawn: Query<Entity>, mut commands: Commands) {
+    for entity in &to_despawn {
+        commands.entity(entity).despawn_recursive();
+    }
+}
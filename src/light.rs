@@ -0,0 +1,130 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use crate::{GameState, enemy::{Burrowed, Enemy}, health::{self, DamageEvent, DamageKind}, obstacle::{Collider, Destructible}, spatial::{self, SpatialGrid}};
+
+pub struct LightPlugin;
+
+impl Plugin for LightPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(Startup, load_light_aura_glow_assets)
+        .add_systems(FixedUpdate, light_aura_damage
+            .after(spatial::index_enemies)
+            .before(health::apply_damage)
+            .run_if(in_state(GameState::Game)))
+        .add_systems(Update, pulse_light_aura_glow.run_if(in_state(GameState::Game)));
+    }
+}
+
+pub const LIGHT_AURA_RADIUS: f32 = 80.0;
+pub const LIGHT_AURA_DAMAGE: u32 = 25;
+pub const LIGHT_AURA_PULSE_SECONDS: f32 = 0.5;
+const LIGHT_AURA_COLOR: Color = Color::rgba(2.0, 1.8, 0.6, 0.12);
+// How much bigger the glow gets right as a pulse fires
+const LIGHT_AURA_PULSE_GROWTH: f32 = 0.15;
+
+
+// The "Lethal Lumens" weapon: every pulse damages each enemy inside the radius
+#[derive(Component)]
+pub struct LightAura {
+    pub radius: f32,
+    pub damage: u32,
+    pub pulse: Timer
+}
+
+impl Default for LightAura {
+    fn default() -> Self {
+        LightAura {
+            radius: LIGHT_AURA_RADIUS,
+            damage: LIGHT_AURA_DAMAGE,
+            pulse: Timer::from_seconds(LIGHT_AURA_PULSE_SECONDS, TimerMode::Repeating),
+        }
+    }
+}
+
+// Tag component for the visible glow, spawned as a child of the entity carrying the `LightAura`
+#[derive(Component)]
+pub struct LightAuraGlow;
+
+// Mesh and material shared by every glow, made once at startup
+#[derive(Resource)]
+pub struct LightAuraGlowAssets {
+    mesh: Mesh2dHandle,
+    material: Handle<ColorMaterial>,
+}
+
+
+// Bundle for the glow drawn under the aura owner, a unit circle scaled up to the aura radius
+pub fn light_aura_glow(
+    glow_assets: &LightAuraGlowAssets,
+    radius: f32) -> (MaterialMesh2dBundle<ColorMaterial>, LightAuraGlow) {
+    (MaterialMesh2dBundle {
+        mesh: glow_assets.mesh.clone(),
+        material: glow_assets.material.clone(),
+        // draw below the owner and the enemies walking into it
+        transform: Transform::from_translation(Vec3::NEG_Z).with_scale(Vec3::splat(radius)),
+        ..default()
+    }, LightAuraGlow)
+}
+
+fn load_light_aura_glow_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands.insert_resource(LightAuraGlowAssets {
+        mesh: meshes.add(shape::Circle::new(1.0).into()).into(),
+        material: materials.add(ColorMaterial::from(LIGHT_AURA_COLOR)),
+    });
+}
+
+fn light_aura_damage(
+    time: Res<Time>,
+    grid: Res<SpatialGrid>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut aura_query: Query<(Entity, &GlobalTransform, &mut LightAura)>,
+    enemy_query: Query<(), (With<Enemy>, Without<Burrowed>)>,
+    prop_query: Query<(Entity, &Transform, &Collider), (With<Destructible>, Without<Enemy>)>) {
+    for (aura_entity, aura_transform, mut aura) in aura_query.iter_mut() {
+        aura.pulse.tick(time.delta());
+        let pulses = aura.pulse.times_finished_this_tick();
+        if pulses == 0 {
+            continue;
+        }
+
+        let aura_position = aura_transform.translation().truncate();
+        let damage = aura.damage * pulses;
+        let mut hit = |target| damage_events.send(DamageEvent {
+            source: Some(aura_entity),
+            target,
+            amount: damage,
+            kind: DamageKind::Light,
+        });
+        for entry in grid.within(aura_position, aura.radius) {
+            if enemy_query.contains(entry.entity) {
+                hit(entry.entity);
+            }
+        }
+        for (prop_entity, prop_transform, collider) in prop_query.iter() {
+            let distance = prop_transform.translation.truncate().distance(aura_position);
+            if distance < aura.radius + collider.bounding_radius() {
+                hit(prop_entity);
+            }
+        }
+    }
+}
+
+// Grows the glow right when a pulse fires and shrinks it back down until the next one
+fn pulse_light_aura_glow(
+    aura_query: Query<(&LightAura, &Children)>,
+    mut glow_query: Query<&mut Transform, With<LightAuraGlow>>) {
+    for (aura, children) in aura_query.iter() {
+        let growth = 1.0 + LIGHT_AURA_PULSE_GROWTH * aura.pulse.percent_left();
+        for &child in children.iter() {
+            if let Ok(mut glow_transform) = glow_query.get_mut(child) {
+                glow_transform.scale = Vec3::splat(aura.radius * growth);
+            }
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use bevy::{prelude::*, sprite::{MaterialMesh2dBundle, Mesh2dHandle}};
+use rand::seq::SliceRandom;
+use crate::{
+    despawn_with_component,
+    enemy,
+    health::{self, HealEvent, Health},
+    light::LightAura,
+    objective::RunProgress,
+    obstacle::{Destructible, Prop, PropKind},
+    player::{self, Player, PlayerStats},
+    progression::{self, XpGemAssets},
+    rng::GameRng,
+    GameState,
+};
+
+// This plugin breaks the props the light has worn down and drops their loot. Every breakable
+// prop rolls once on its weighted loot table, chests always give something good.
+// Pickups fly to the player like experience gems do, buffs wear off after a while.
+pub struct LootPlugin;
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_event::<PropBroken>()
+        .add_systems(Startup, load_gold_assets)
+        .add_systems(FixedUpdate, (
+            break_props.after(enemy::despawn_dead_enemies),
+            collect_pickups,
+            expire_buffs,
+        ).chain().before(health::apply_healing).run_if(in_state(GameState::Game)))
+        .add_systems(OnEnter(GameState::Menu), despawn_with_component::<Pickup>);
+    }
+}
+
+const PICKUP_SPRITE_SCALE: f32 = 2.0;
+const PICKUP_SPEED: f32 = 500.0;
+// Heals at least this big look like a potion, smaller ones like berries
+const BIG_HEAL: u32 = 30;
+const GOLD_SIZE: f32 = 4.0;
+const GOLD_COLOR: Color = Color::rgb(3.0, 2.2, 0.3);
+const HASTE_MULTIPLIER: f32 = 1.4;
+const HASTE_SECONDS: f32 = 8.0;
+const RADIANCE_MULTIPLIER: f32 = 1.3;
+const RADIANCE_SECONDS: f32 = 10.0;
+
+pub const BARREL_LOOT: &[LootEntry] = &[
+    LootEntry { loot: None, weight: 40 },
+    LootEntry { loot: Some(Loot::Heal(10)), weight: 25 },
+    LootEntry { loot: Some(Loot::Gold(5)), weight: 25 },
+    LootEntry { loot: Some(Loot::Xp(3)), weight: 10 },
+];
+pub const CRATE_LOOT: &[LootEntry] = &[
+    LootEntry { loot: None, weight: 20 },
+    LootEntry { loot: Some(Loot::Heal(30)), weight: 20 },
+    LootEntry { loot: Some(Loot::Gold(10)), weight: 30 },
+    LootEntry { loot: Some(Loot::Xp(5)), weight: 15 },
+    LootEntry { loot: Some(Loot::Buff(Buff::Haste)), weight: 15 },
+];
+pub const CHEST_LOOT: &[LootEntry] = &[
+    LootEntry { loot: Some(Loot::Heal(50)), weight: 25 },
+    LootEntry { loot: Some(Loot::Gold(50)), weight: 30 },
+    LootEntry { loot: Some(Loot::Buff(Buff::Haste)), weight: 20 },
+    LootEntry { loot: Some(Loot::Buff(Buff::Radiance)), weight: 25 },
+];
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Loot {
+    // Hit points back, anything past the maximum goes to the shield
+    Heal(u32),
+    Buff(Buff),
+    Gold(u32),
+    Xp(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Buff {
+    // Faster movement
+    Haste,
+    // Wider light
+    Radiance,
+}
+
+impl Buff {
+    fn seconds(self) -> f32 {
+        match self {
+            Buff::Haste => HASTE_SECONDS,
+            Buff::Radiance => RADIANCE_SECONDS,
+        }
+    }
+
+    // Called with the inverse multiplier when the buff wears off, so upgrades picked meanwhile stay
+    fn scale(self, multiplier: f32, stats: &mut PlayerStats, aura: &mut LightAura) {
+        match self {
+            Buff::Haste => stats.speed *= multiplier,
+            Buff::Radiance => aura.radius *= multiplier,
+        }
+    }
+
+    fn multiplier(self) -> f32 {
+        match self {
+            Buff::Haste => HASTE_MULTIPLIER,
+            Buff::Radiance => RADIANCE_MULTIPLIER,
+        }
+    }
+}
+
+// One line of a loot table, `None` drops nothing
+#[derive(Debug, Clone, Copy)]
+pub struct LootEntry {
+    pub loot: Option<Loot>,
+    pub weight: u32,
+}
+
+#[derive(Component)]
+pub struct Pickup(pub Loot);
+
+// Mesh and material shared by every gold pickup, made once at startup
+#[derive(Resource)]
+pub struct GoldAssets {
+    mesh: Mesh2dHandle,
+    material: Handle<ColorMaterial>,
+}
+
+// Buffs the player has right now, with the time they have left
+#[derive(Component, Default)]
+pub struct ActiveBuffs(Vec<(Buff, Timer)>);
+
+#[derive(Event)]
+pub struct PropBroken {
+    pub kind: PropKind,
+    pub position: Vec2,
+}
+
+
+fn load_gold_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(GoldAssets {
+        mesh: meshes.add(shape::Circle::new(GOLD_SIZE).into()).into(),
+        material: materials.add(ColorMaterial::from(GOLD_COLOR)),
+    });
+}
+
+fn roll_loot(table: &[LootEntry], rng: &mut GameRng) -> Option<Loot> {
+    table.choose_weighted(&mut rng.0, |entry| entry.weight).ok().and_then(|entry| entry.loot)
+}
+
+fn spawn_pickup(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    gem_assets: &XpGemAssets,
+    gold_assets: &GoldAssets,
+    loot: Loot,
+    position: Vec2,
+) {
+    let texture = match loot {
+        Loot::Heal(amount) if amount >= BIG_HEAL => "textures/rpg/props/generic-rpg-loot03.png",
+        Loot::Heal(_) => "textures/rpg/props/generic-rpg-loot05.png",
+        Loot::Buff(Buff::Haste) => "textures/rpg/props/generic-rpg-loot04.png",
+        Loot::Buff(Buff::Radiance) => "textures/rpg/props/generic-rpg-loot01.png",
+        // Experience is the same gem enemies drop
+        Loot::Xp(value) => {
+            progression::spawn_xp_gem(commands, gem_assets, value, position);
+            return;
+        }
+        // There is no coin in the props, gold is a little glowing dot
+        Loot::Gold(_) => {
+            commands.spawn((MaterialMesh2dBundle {
+                mesh: gold_assets.mesh.clone(),
+                material: gold_assets.material.clone(),
+                transform: Transform::from_translation(position.extend(-0.5)),
+                ..default()
+                },
+                Pickup(loot),
+            ));
+            return;
+        }
+    };
+    commands.spawn((SpriteBundle {
+        texture: asset_server.load(texture),
+        transform: Transform::from_translation(position.extend(-0.5)).with_scale(Vec3::splat(PICKUP_SPRITE_SCALE)),
+        ..default()
+        },
+        Pickup(loot),
+    ));
+}
+
+pub fn break_props(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    gem_assets: Res<XpGemAssets>,
+    gold_assets: Res<GoldAssets>,
+    mut rng: ResMut<GameRng>,
+    mut broken_events: EventWriter<PropBroken>,
+    mut query: Query<(Entity, &Transform, &Prop, &Health, &mut Handle<Image>), With<Destructible>>,
+) {
+    for (entity, transform, prop, health, mut texture) in query.iter_mut() {
+        if health.hit_points > 0 {
+            continue;
+        }
+        let definition = prop.0.definition();
+        let position = transform.translation.truncate();
+        broken_events.send(PropBroken { kind: prop.0, position });
+
+        match definition.opened_texture {
+            // An opened chest stays in the way, it just can't be looted again
+            Some(opened_texture) => {
+                *texture = asset_server.load(opened_texture);
+                commands.entity(entity).remove::<(Destructible, Health)>();
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+
+        if let Some(loot) = roll_loot(definition.loot, &mut rng) {
+            // Drop it in front of the prop, where it can be reached
+            let drop_position = position - Vec2::Y * definition.collider.bounding_radius();
+            spawn_pickup(&mut commands, &asset_server, &gem_assets, &gold_assets, loot, drop_position);
+        }
+    }
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut progress: ResMut<RunProgress>,
+    mut heal_events: EventWriter<HealEvent>,
+    mut player_query: Query<(Entity, &Transform, &mut PlayerStats, &mut LightAura, Option<&mut ActiveBuffs>), With<Player>>,
+    mut pickup_query: Query<(Entity, &mut Transform, &Pickup), Without<Player>>,
+) {
+    let (player_entity, player_transform, mut stats, mut aura, active_buffs) = player_query.single_mut();
+    let player_position = player_transform.translation.truncate();
+
+    let mut new_buffs = Vec::new();
+    for (pickup_entity, mut pickup_transform, pickup) in pickup_query.iter_mut() {
+        let to_player = player_position - pickup_transform.translation.truncate();
+        let distance = to_player.length();
+        if distance >= player::PLAYER_SIZE {
+            if distance < stats.pickup_radius {
+                let step = to_player.normalize() * (PICKUP_SPEED * time.delta_seconds()).min(distance);
+                pickup_transform.translation += step.extend(0.);
+            }
+            continue;
+        }
+
+        commands.entity(pickup_entity).despawn();
+        match pickup.0 {
+            Loot::Heal(amount) => heal_events.send(HealEvent { target: player_entity, amount }),
+            Loot::Gold(amount) => progress.gold += amount,
+            Loot::Buff(buff) => {
+                buff.scale(buff.multiplier(), &mut stats, &mut aura);
+                new_buffs.push((buff, Timer::from_seconds(buff.seconds(), TimerMode::Once)));
+            }
+            // Experience drops as a gem, see `spawn_pickup`
+            Loot::Xp(_) => {}
+        }
+    }
+
+    if new_buffs.is_empty() {
+        return;
+    }
+    match active_buffs {
+        Some(mut active_buffs) => active_buffs.0.extend(new_buffs),
+        None => {
+            commands.entity(player_entity).insert(ActiveBuffs(new_buffs));
+        }
+    }
+}
+
+fn expire_buffs(
+    time: Res<Time>,
+    mut query: Query<(&mut ActiveBuffs, &mut PlayerStats, &mut LightAura), With<Player>>,
+) {
+    for (mut active_buffs, mut stats, mut aura) in query.iter_mut() {
+        active_buffs.0.retain_mut(|(buff, timer)| {
+            if !timer.tick(time.delta()).finished() {
+                return true;
+            }
+            buff.scale(1.0 / buff.multiplier(), &mut stats, &mut aura);
+            false
+        });
+    }
+}
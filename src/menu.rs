@@ -1,11 +1,12 @@
 use bevy::{app::AppExit, prelude::*};
 
-use crate::{despawn_all, player::Player, health::Health};
+use crate::{despawn_all, player::Player, health::Health, hero::{Hero, HeroAssets, SelectedHero}, rng::GameSeed, sound::SoundEffect};
 
 use super::{despawn_with_component, DisplayQuality, GameState, Volume, TEXT_COLOR};
 
-// This plugin manages the menu, with 5 different screens:
-// - a main menu with "New Game", "Settings", "Quit"
+// This plugin manages the menu, with 6 different screens:
+// - a main menu with "New Game", "Settings", "Quit", and "Replay Seed" once a run was played
+// - a character select screen, picking a hero starts the run
 // - a settings menu with two submenus and a back button
 // - two settings screen with a setting that can be set and a back button
 pub struct MenuPlugin;
@@ -16,14 +17,31 @@ impl Plugin for MenuPlugin {
             .add_state::<MenuState>()
             .add_systems(OnEnter(GameState::Menu), (despawn_with_component::<Health>, menu_setup, main_menu_setup).chain())
             .add_systems(OnExit(MenuState::Main), despawn_with_component::<OnMainMenuScreen>)
-            .add_systems(Update, (menu_action, button_system).chain().run_if(in_state(GameState::Menu)));
+            // Systems to handle the character select screen
+            .add_systems(OnEnter(MenuState::CharacterSelect), character_select_setup)
+            .add_systems(OnExit(MenuState::CharacterSelect), despawn_with_component::<OnCharacterSelectScreen>)
+            // Systems to handle the settings menu screen
+            .add_systems(OnEnter(MenuState::Settings), settings_menu_setup)
+            .add_systems(OnExit(MenuState::Settings), despawn_with_component::<OnSettingsMenuScreen>)
+            // Systems to handle the display settings screen
+            .add_systems(OnEnter(MenuState::SettingsDisplay), display_settings_menu_setup)
+            .add_systems(Update, setting_button::<DisplayQuality>.run_if(in_state(MenuState::SettingsDisplay)))
+            .add_systems(OnExit(MenuState::SettingsDisplay), despawn_with_component::<OnDisplaySettingsMenuScreen>)
+            // Systems to handle the sound settings screen
+            .add_systems(OnEnter(MenuState::SettingsSound), sound_settings_menu_setup)
+            .add_systems(Update, setting_button::<Volume>.run_if(in_state(MenuState::SettingsSound)))
+            .add_systems(OnExit(MenuState::SettingsSound), despawn_with_component::<OnSoundSettingsMenuScreen>)
+            // The settings screens can also be opened from the pause screen
+            .add_systems(Update, (menu_action, button_system).chain().run_if(in_state(GameState::Menu).or_else(in_state(GameState::Pause))))
+            .add_systems(Update, button_system.run_if(in_state(GameState::LevelUp)));
     }
 }
 
 // State used for the current menu screen
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
-enum MenuState {
+pub enum MenuState {
     Main,
+    CharacterSelect,
     Settings,
     SettingsDisplay,
     SettingsSound,
@@ -35,6 +53,10 @@ enum MenuState {
 #[derive(Component)]
 struct OnMainMenuScreen;
 
+// Tag component used to tag entities added on the character select screen
+#[derive(Component)]
+struct OnCharacterSelectScreen;
+
 // Tag component used to tag entities added on the settings menu screen
 #[derive(Component)]
 struct OnSettingsMenuScreen;
@@ -47,11 +69,13 @@ struct OnDisplaySettingsMenuScreen;
 #[derive(Component)]
 struct OnSoundSettingsMenuScreen;
 
-const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+pub const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
 const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
 const HOVERED_PRESSED_BUTTON: Color = Color::rgb(0.25, 0.65, 0.25);
 const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
-const BACKGROUND: Color = Color::rgb(0.705, 0.302, 0.027);
+pub const BACKGROUND: Color = Color::rgb(0.705, 0.302, 0.027);
+// Volume goes from 0 (muted) to this
+pub const MAX_VOLUME: u32 = 9;
 
 // Tag component used to mark which setting is currently selected
 #[derive(Component)]
@@ -61,6 +85,9 @@ struct SelectedOption;
 #[derive(Component)]
 enum MenuButtonAction {
     Play,
+    // Play the seed of the last run again
+    ReplaySeed,
+    PlayAs(Hero),
     Settings,
     SettingsDisplay,
     SettingsSound,
@@ -75,8 +102,12 @@ fn button_system(
         (&Interaction, &mut BackgroundColor, Option<&SelectedOption>),
         (Changed<Interaction>, With<Button>),
     >,
+    mut sound_effects: EventWriter<SoundEffect>,
 ) {
     for (interaction, mut color, selected) in &mut interaction_query {
+        if *interaction == Interaction::Pressed {
+            sound_effects.send(SoundEffect::ButtonClick);
+        }
         *color = match (*interaction, selected) {
             (Interaction::Pressed, _) | (Interaction::None, Some(_)) => PRESSED_BUTTON.into(),
             (Interaction::Hovered, Some(_)) => HOVERED_PRESSED_BUTTON.into(),
@@ -86,11 +117,30 @@ fn button_system(
     }
 }
 
+// This system updates the settings when a new value for a setting is selected, and marks
+// the button as the one currently selected
+fn setting_button<T: Resource + Component + PartialEq + Copy>(
+    interaction_query: Query<(&Interaction, &T, Entity), (Changed<Interaction>, With<Button>)>,
+    mut selected_query: Query<(Entity, &mut BackgroundColor), With<SelectedOption>>,
+    mut commands: Commands,
+    mut setting: ResMut<T>,
+) {
+    for (interaction, button_setting, entity) in &interaction_query {
+        if *interaction == Interaction::Pressed && *setting != *button_setting {
+            let (previous_button, mut previous_color) = selected_query.single_mut();
+            *previous_color = NORMAL_BUTTON.into();
+            commands.entity(previous_button).remove::<SelectedOption>();
+            commands.entity(entity).insert(SelectedOption);
+            *setting = *button_setting;
+        }
+    }
+}
+
 fn menu_setup(mut menu_state: ResMut<NextState<MenuState>>) {
     menu_state.set(MenuState::Main);
 }
 
-fn main_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn main_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>, seed: Res<GameSeed>) {
     let font = asset_server.load("fonts/FiraSans-Bold.ttf");
     // Common style for all buttons on the screen
     let button_style = Style {
@@ -182,6 +232,50 @@ fn main_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                                 button_text_style.clone(),
                             ));
                         });
+                    if seed.last.is_some() {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: button_style.clone(),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                MenuButtonAction::ReplaySeed,
+                            ))
+                            .with_children(|parent| {
+                                let icon = asset_server.load("textures/Game Icons/right.png");
+                                parent.spawn(ImageBundle {
+                                    style: button_icon_style.clone(),
+                                    image: UiImage::new(icon),
+                                    ..default()
+                                });
+                                parent.spawn(TextBundle::from_section(
+                                    "Replay Seed",
+                                    button_text_style.clone(),
+                                ));
+                            });
+                    }
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style.clone(),
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::Settings,
+                        ))
+                        .with_children(|parent| {
+                            let icon = asset_server.load("textures/Game Icons/wrench.png");
+                            parent.spawn(ImageBundle {
+                                style: button_icon_style.clone(),
+                                image: UiImage::new(icon),
+                                ..default()
+                            });
+                            parent.spawn(TextBundle::from_section(
+                                "Settings",
+                                button_text_style.clone(),
+                            ));
+                        });
                     parent
                         .spawn((
                             ButtonBundle {
@@ -210,6 +304,9 @@ fn menu_action(
         (Changed<Interaction>, With<Button>),
     >,
     mut app_exit_events: EventWriter<AppExit>,
+    current_game_state: Res<State<GameState>>,
+    mut selected_hero: ResMut<SelectedHero>,
+    mut seed: ResMut<GameSeed>,
     mut menu_state: ResMut<NextState<MenuState>>,
     mut game_state: ResMut<NextState<GameState>>,
 ) {
@@ -218,6 +315,15 @@ fn menu_action(
             match menu_button_action {
                 MenuButtonAction::Quit => app_exit_events.send(AppExit),
                 MenuButtonAction::Play => {
+                    seed.replay = false;
+                    menu_state.set(MenuState::CharacterSelect);
+                }
+                MenuButtonAction::ReplaySeed => {
+                    seed.replay = true;
+                    menu_state.set(MenuState::CharacterSelect);
+                }
+                MenuButtonAction::PlayAs(hero) => {
+                    selected_hero.0 = *hero;
                     menu_state.set(MenuState::Disabled);
                     game_state.set(GameState::Game);
                 }
@@ -228,11 +334,376 @@ fn menu_action(
                 MenuButtonAction::SettingsSound => {
                     menu_state.set(MenuState::SettingsSound);
                 }
-                MenuButtonAction::BackToMainMenu => menu_state.set(MenuState::Main),
+                // When the settings were opened from the pause screen, go back to it instead
+                MenuButtonAction::BackToMainMenu => match current_game_state.get() {
+                    GameState::Pause => menu_state.set(MenuState::Disabled),
+                    _ => menu_state.set(MenuState::Main),
+                },
                 MenuButtonAction::BackToSettings => {
                     menu_state.set(MenuState::Settings);
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// Common layout for the screens after the main menu: a full screen node with a column in the middle
+fn menu_screen<T: Component>(tag: T) -> (NodeBundle, T) {
+    (
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        },
+        tag,
+    )
+}
+
+fn menu_column() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: BACKGROUND.into(),
+        ..default()
+    }
+}
+
+fn settings_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let button_style = Style {
+        width: Val::Px(200.0),
+        height: Val::Px(65.0),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    let button_text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 40.0,
+        color: TEXT_COLOR,
+    };
+
+    commands
+        .spawn(menu_screen(OnSettingsMenuScreen))
+        .with_children(|parent| {
+            parent
+                .spawn(menu_column())
+                .with_children(|parent| {
+                    for (action, text) in [
+                        (MenuButtonAction::SettingsDisplay, "Display"),
+                        (MenuButtonAction::SettingsSound, "Sound"),
+                        (MenuButtonAction::BackToMainMenu, "Back"),
+                    ] {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: button_style.clone(),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                action,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    text,
+                                    button_text_style.clone(),
+                                ));
+                            });
+                    }
+                });
+        });
+}
+
+fn display_settings_menu_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    display_quality: Res<DisplayQuality>,
+) {
+    let button_style = Style {
+        width: Val::Px(200.0),
+        height: Val::Px(65.0),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 40.0,
+        color: TEXT_COLOR,
+    };
+
+    commands
+        .spawn(menu_screen(OnDisplaySettingsMenuScreen))
+        .with_children(|parent| {
+            parent
+                .spawn(menu_column())
+                .with_children(|parent| {
+                    // Create a new `NodeBundle`, this time not setting its `flex_direction`. It will
+                    // use the default value, `FlexDirection::Row`, from left to right.
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: BACKGROUND.into(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                "Display Quality",
+                                button_text_style.clone(),
+                            ));
+                            // Display a button for each possible value
+                            for quality_setting in [
+                                DisplayQuality::Low,
+                                DisplayQuality::Medium,
+                                DisplayQuality::High,
+                            ] {
+                                let mut entity = parent.spawn((
+                                    ButtonBundle {
+                                        style: Style {
+                                            width: Val::Px(150.0),
+                                            height: Val::Px(65.0),
+                                            ..button_style.clone()
+                                        },
+                                        background_color: NORMAL_BUTTON.into(),
+                                        ..default()
+                                    },
+                                    quality_setting,
+                                ));
+                                entity.with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        format!("{quality_setting:?}"),
+                                        button_text_style.clone(),
+                                    ));
+                                });
+                                if *display_quality == quality_setting {
+                                    entity.insert((SelectedOption, BackgroundColor(PRESSED_BUTTON)));
+                                }
+                            }
+                        });
+                    // Display the back button to return to the settings screen
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style,
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::BackToSettings,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section("Back", button_text_style));
+                        });
+                });
+        });
+}
+
+fn sound_settings_menu_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    volume: Res<Volume>,
+) {
+    let button_style = Style {
+        width: Val::Px(200.0),
+        height: Val::Px(65.0),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 40.0,
+        color: TEXT_COLOR,
+    };
+
+    commands
+        .spawn(menu_screen(OnSoundSettingsMenuScreen))
+        .with_children(|parent| {
+            parent
+                .spawn(menu_column())
+                .with_children(|parent| {
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: BACKGROUND.into(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                "Volume",
+                                button_text_style.clone(),
+                            ));
+                            // One small button per volume step, the selected one shows the current volume
+                            for volume_setting in 0..=MAX_VOLUME {
+                                let mut entity = parent.spawn((
+                                    ButtonBundle {
+                                        style: Style {
+                                            width: Val::Px(30.0),
+                                            height: Val::Px(65.0),
+                                            ..button_style.clone()
+                                        },
+                                        background_color: NORMAL_BUTTON.into(),
+                                        ..default()
+                                    },
+                                    Volume(volume_setting),
+                                ));
+                                if *volume == Volume(volume_setting) {
+                                    entity.insert((SelectedOption, BackgroundColor(PRESSED_BUTTON)));
+                                }
+                            }
+                        });
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style,
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::BackToSettings,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section("Back", button_text_style));
+                        });
+                });
+        });
+}
+
+fn character_select_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    hero_assets: Res<HeroAssets>,
+    selected_hero: Res<SelectedHero>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let card_style = Style {
+        width: Val::Px(200.0),
+        height: Val::Px(300.0),
+        margin: UiRect::all(Val::Px(15.0)),
+        padding: UiRect::all(Val::Px(10.0)),
+        flex_direction: FlexDirection::Column,
+        justify_content: JustifyContent::SpaceEvenly,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let name_style = TextStyle {
+        font: font.clone(),
+        font_size: 32.0,
+        color: TEXT_COLOR,
+    };
+    let detail_style = TextStyle {
+        font: font.clone(),
+        font_size: 18.0,
+        color: TEXT_COLOR,
+    };
+    let button_text_style = TextStyle {
+        font: font.clone(),
+        font_size: 40.0,
+        color: TEXT_COLOR,
+    };
+
+    commands
+        .spawn(menu_screen(OnCharacterSelectScreen))
+        .with_children(|parent| {
+            parent
+                .spawn(menu_column())
+                .with_children(|parent| {
+                    parent.spawn(
+                        TextBundle::from_section(
+                            "Choose your hero",
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 60.0,
+                                color: TEXT_COLOR,
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::all(Val::Px(30.0)),
+                            ..default()
+                        }),
+                    );
+
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for hero in Hero::ALL {
+                                let definition = hero.definition();
+                                let mut card = parent.spawn((
+                                    ButtonBundle {
+                                        style: card_style.clone(),
+                                        background_color: NORMAL_BUTTON.into(),
+                                        ..default()
+                                    },
+                                    MenuButtonAction::PlayAs(hero),
+                                ));
+                                card.with_children(|parent| {
+                                    parent.spawn(AtlasImageBundle {
+                                        style: Style {
+                                            width: Val::Px(definition.frame_size.x * 3.0),
+                                            height: Val::Px(definition.frame_size.y * 3.0),
+                                            ..default()
+                                        },
+                                        texture_atlas: hero_assets.atlas(hero),
+                                        ..default()
+                                    });
+                                    parent.spawn(TextBundle::from_section(definition.name, name_style.clone()));
+                                    for line in [
+                                        format!("HP {}", definition.max_hit_points),
+                                        format!("Speed {:.0}", definition.speed),
+                                        format!("Light {:.0}", definition.light_radius),
+                                    ] {
+                                        parent.spawn(TextBundle::from_section(line, detail_style.clone()));
+                                    }
+                                    parent.spawn(TextBundle::from_section(definition.ability.name(), name_style.clone()));
+                                    parent.spawn(TextBundle::from_section(definition.ability.description(), detail_style.clone()));
+                                });
+                                // Highlight the hero of the last run
+                                if selected_hero.0 == hero {
+                                    card.insert((SelectedOption, BackgroundColor(PRESSED_BUTTON)));
+                                }
+                            }
+                        });
+
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(200.0),
+                                    height: Val::Px(65.0),
+                                    margin: UiRect::all(Val::Px(20.0)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::BackToMainMenu,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section("Back", button_text_style));
+                        });
+                });
+        });
+}
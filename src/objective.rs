@@ -0,0 +1,142 @@
+use std::time::Duration;
+use bevy::prelude::*;
+use crate::{despawn_with_component, enemy::{self, EnemyKilled}, wave::WaveTimer, GameState, TEXT_COLOR};
+
+// This plugin tracks what the player has to do to win a run, shows the progress
+// at the top of the screen and moves to `GameState::GameWon` once it is met
+pub struct ObjectivePlugin;
+
+impl Plugin for ObjectivePlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<RunObjective>()
+        .init_resource::<RunProgress>()
+        .add_systems(OnExit(GameState::Menu), (reset_run_progress, objective_display_setup))
+        .add_systems(OnEnter(GameState::Menu), despawn_with_component::<OnObjectiveDisplay>)
+        .add_systems(FixedUpdate, (
+            count_kills.after(enemy::despawn_dead_enemies),
+            check_objective,
+        ).run_if(in_state(GameState::Game)))
+        .add_systems(Update, update_objective_display.run_if(in_state(GameState::Game)));
+    }
+}
+
+pub const OBJECTIVE_SURVIVE_SECONDS: u64 = 300;
+
+
+// What has to happen for the run to be won, insert a different one to change the goal
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunObjective {
+    Survive(Duration),
+    ClearWaves(u32),
+    DefeatBosses(u32),
+}
+
+impl Default for RunObjective {
+    fn default() -> Self {
+        RunObjective::Survive(Duration::from_secs(OBJECTIVE_SURVIVE_SECONDS))
+    }
+}
+
+// How far the current run has come along
+#[derive(Resource, Default)]
+pub struct RunProgress {
+    pub elapsed: Duration,
+    pub kills: u32,
+    pub bosses_defeated: u32,
+    // Picked up from broken props
+    pub gold: u32,
+}
+
+// Tag component used to tag entities added for the objective display
+#[derive(Component)]
+struct OnObjectiveDisplay;
+
+#[derive(Component)]
+struct ObjectiveText;
+
+
+fn reset_run_progress(mut progress: ResMut<RunProgress>) {
+    *progress = RunProgress::default();
+}
+
+fn count_kills(mut killed_events: EventReader<EnemyKilled>, mut progress: ResMut<RunProgress>) {
+    progress.kills += killed_events.read().count() as u32;
+}
+
+fn check_objective(
+    time: Res<Time>,
+    objective: Res<RunObjective>,
+    wave_timer: Res<WaveTimer>,
+    mut progress: ResMut<RunProgress>,
+    mut game_state: ResMut<NextState<GameState>>
+) {
+    progress.elapsed += time.delta();
+
+    let completed = match *objective {
+        RunObjective::Survive(duration) => progress.elapsed >= duration,
+        RunObjective::ClearWaves(waves) => wave_timer.waves >= waves,
+        RunObjective::DefeatBosses(bosses) => progress.bosses_defeated >= bosses,
+    };
+    if completed {
+        game_state.set(GameState::GameWon);
+    }
+}
+
+fn objective_text(objective: &RunObjective, progress: &RunProgress, wave_timer: &WaveTimer) -> String {
+    match *objective {
+        RunObjective::Survive(duration) => {
+            let remaining = duration.saturating_sub(progress.elapsed).as_secs();
+            format!("Survive {}:{:02}", remaining / 60, remaining % 60)
+        }
+        RunObjective::ClearWaves(waves) => {
+            format!("Waves {} / {}", wave_timer.waves.min(waves), waves)
+        }
+        RunObjective::DefeatBosses(bosses) => {
+            format!("Bosses {} / {}", progress.bosses_defeated.min(bosses), bosses)
+        }
+    }
+}
+
+fn objective_display_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnObjectiveDisplay,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font,
+                        font_size: 40.0,
+                        color: TEXT_COLOR,
+                    },
+                ),
+                ObjectiveText,
+            ));
+        });
+}
+
+fn update_objective_display(
+    objective: Res<RunObjective>,
+    progress: Res<RunProgress>,
+    wave_timer: Res<WaveTimer>,
+    mut query: Query<&mut Text, With<ObjectiveText>>
+) {
+    for mut text in query.iter_mut() {
+        text.sections[0].value = objective_text(&objective, &progress, &wave_timer);
+    }
+}
@@ -0,0 +1,255 @@
+use bevy::{prelude::*, sprite::Anchor};
+use serde::Deserialize;
+use crate::{
+    enemy::Enemy,
+    health::Health,
+    loot::{self, LootEntry},
+    player::Player,
+};
+
+// Props that stand in the way, placed by the arena layouts. The player and enemies are
+// treated as circles and pushed out of any collider they walk into, which makes them slide
+// along it. Enemies also look ahead and steer around whatever is between them and the player.
+// Barrels, crates and chests can be broken by the light, see `loot::LootPlugin`.
+
+// Props are pixel art like everything else, draw them bigger than their source images
+const PROP_SPRITE_SCALE: f32 = 2.0;
+// Rocks, fences and crates are drawn under the characters, tree tops over them
+const PROP_LAYER: f32 = -1.0;
+const CANOPY_LAYER: f32 = 2.0;
+// How far ahead enemies look for obstacles
+const AVOIDANCE_DISTANCE: f32 = 80.0;
+// How hard enemies turn away from an obstacle ahead, relative to heading for the player
+const AVOIDANCE_STRENGTH: f32 = 1.5;
+// Pushing out of one collider can push into another, a few passes sort out corners
+const RESOLVE_PASSES: usize = 3;
+
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PropKind {
+    Rock,
+    Boulder,
+    Tree,
+    Fence,
+    Crate,
+    Barrel,
+    Chest,
+}
+
+pub struct PropDefinition {
+    pub texture: &'static str,
+    pub collider: Collider,
+    // Where the collider sits in the sprite, see `Sprite::anchor`
+    pub anchor: Anchor,
+    pub layer: f32,
+    // Props with hit points can be broken by the light
+    pub hit_points: Option<u32>,
+    // Broken props with an opened texture stay where they are and switch to it
+    pub opened_texture: Option<&'static str>,
+    pub loot: &'static [LootEntry],
+}
+
+impl PropKind {
+    pub fn definition(self) -> PropDefinition {
+        match self {
+            PropKind::Rock => PropDefinition {
+                texture: "textures/rpg/props/generic-rpg-rock05.png",
+                collider: Collider::Circle { radius: 16.0 },
+                anchor: Anchor::Center,
+                layer: PROP_LAYER,
+                hit_points: None,
+                opened_texture: None,
+                loot: &[],
+            },
+            PropKind::Boulder => PropDefinition {
+                texture: "textures/rpg/props/generic-rpg-rock06.png",
+                collider: Collider::Box { half_size: Vec2::new(28.0, 10.0) },
+                anchor: Anchor::Center,
+                layer: PROP_LAYER,
+                hit_points: None,
+                opened_texture: None,
+                loot: &[],
+            },
+            // Only the trunk is in the way, the top of the tree can be walked behind
+            PropKind::Tree => PropDefinition {
+                texture: "textures/rpg/props/generic-rpg-tree01.png",
+                collider: Collider::Circle { radius: 14.0 },
+                anchor: Anchor::Custom(Vec2::new(0.0, -0.35)),
+                layer: CANOPY_LAYER,
+                hit_points: None,
+                opened_texture: None,
+                loot: &[],
+            },
+            PropKind::Fence => PropDefinition {
+                texture: "textures/rpg/props/generic-rpg-fence02.png",
+                collider: Collider::Box { half_size: Vec2::new(16.0, 6.0) },
+                anchor: Anchor::Center,
+                layer: PROP_LAYER,
+                hit_points: None,
+                opened_texture: None,
+                loot: &[],
+            },
+            PropKind::Crate => PropDefinition {
+                texture: "textures/rpg/props/generic-rpg-crate01.png",
+                collider: Collider::Box { half_size: Vec2::new(17.0, 17.0) },
+                anchor: Anchor::Center,
+                layer: PROP_LAYER,
+                hit_points: Some(60),
+                opened_texture: None,
+                loot: loot::CRATE_LOOT,
+            },
+            PropKind::Barrel => PropDefinition {
+                texture: "textures/rpg/props/generic-rpg-barrel01.png",
+                collider: Collider::Circle { radius: 12.0 },
+                anchor: Anchor::Center,
+                layer: PROP_LAYER,
+                hit_points: Some(40),
+                opened_texture: None,
+                loot: loot::BARREL_LOOT,
+            },
+            PropKind::Chest => PropDefinition {
+                texture: "textures/rpg/props/generic-rpg-treasure-closed.png",
+                collider: Collider::Box { half_size: Vec2::new(24.0, 18.0) },
+                anchor: Anchor::Center,
+                layer: PROP_LAYER,
+                hit_points: Some(150),
+                opened_texture: Some("textures/rpg/props/generic-rpg-trasure-open.png"),
+                loot: loot::CHEST_LOOT,
+            },
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Prop(pub PropKind);
+
+// Marks props that can still be broken
+#[derive(Component)]
+pub struct Destructible;
+
+// The solid part of a prop, centered on its transform
+#[derive(Component, Debug, Clone, Copy)]
+pub enum Collider {
+    Circle { radius: f32 },
+    // Axis aligned box
+    Box { half_size: Vec2 },
+}
+
+impl Collider {
+    // Radius of a circle around the whole collider
+    pub fn bounding_radius(&self) -> f32 {
+        match *self {
+            Collider::Circle { radius } => radius,
+            Collider::Box { half_size } => half_size.length(),
+        }
+    }
+
+    // Where a circle at `position` has to move to so it doesn't overlap the collider, if it does
+    pub fn push_out(&self, center: Vec2, position: Vec2, radius: f32) -> Option<Vec2> {
+        match *self {
+            Collider::Circle { radius: collider_radius } => {
+                let offset = position - center;
+                let min_distance = collider_radius + radius;
+                if offset.length_squared() >= min_distance * min_distance {
+                    return None;
+                }
+                let direction = offset.try_normalize().unwrap_or(Vec2::X);
+                Some(center + direction * min_distance)
+            }
+            Collider::Box { half_size } => {
+                let closest = position.clamp(center - half_size, center + half_size);
+                let offset = position - closest;
+                if offset.length_squared() >= radius * radius {
+                    return None;
+                }
+                if let Some(direction) = offset.try_normalize() {
+                    return Some(closest + direction * radius);
+                }
+                // The center of the circle is inside the box, leave through the nearest side
+                let inside = position - center;
+                let depth = half_size - inside.abs();
+                if depth.x < depth.y {
+                    Some(Vec2::new(center.x + inside.x.signum() * (half_size.x + radius), position.y))
+                } else {
+                    Some(Vec2::new(position.x, center.y + inside.y.signum() * (half_size.y + radius)))
+                }
+            }
+        }
+    }
+}
+
+// Everything solid, read by the movement systems
+pub type ColliderQuery<'w, 's> = Query<'w, 's, (&'static Transform, &'static Collider), (Without<Player>, Without<Enemy>)>;
+
+pub fn colliders(query: &ColliderQuery) -> Vec<(Vec2, Collider)> {
+    query.iter().map(|(transform, collider)| (transform.translation.truncate(), *collider)).collect()
+}
+
+// Moves a circle out of every collider it overlaps
+pub fn resolve_collisions(mut position: Vec2, radius: f32, colliders: &[(Vec2, Collider)]) -> Vec2 {
+    for _ in 0..RESOLVE_PASSES {
+        let mut moved = false;
+        for (center, collider) in colliders {
+            if let Some(resolved) = collider.push_out(*center, position, radius) {
+                position = resolved;
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+    position
+}
+
+// Turns `direction` away from the nearest collider in the way of a circle heading to `target`
+pub fn avoid_obstacles(position: Vec2, target: Vec2, direction: Vec2, radius: f32, colliders: &[(Vec2, Collider)]) -> Vec2 {
+    let look_ahead = AVOIDANCE_DISTANCE.min(position.distance(target));
+    let mut nearest: Option<(f32, Vec2)> = None;
+    for (center, collider) in colliders {
+        let clearance = collider.bounding_radius() + radius;
+        // How far along the way the obstacle is, and how far it is off to the side
+        let along = (*center - position).dot(direction);
+        if along <= 0.0 || along > look_ahead + clearance {
+            continue;
+        }
+        let side = position + direction * along - *center;
+        if side.length() >= clearance {
+            continue;
+        }
+        if nearest.is_none_or(|(distance, _)| along < distance) {
+            // Heading straight for its center, either side will do
+            let away = side.try_normalize().unwrap_or(direction.perp());
+            nearest = Some((along, away));
+        }
+    }
+
+    match nearest {
+        Some((_, away)) => (direction + away * AVOIDANCE_STRENGTH).normalize_or_zero(),
+        None => direction,
+    }
+}
+
+pub fn spawn_prop(commands: &mut Commands, asset_server: &AssetServer, kind: PropKind, position: Vec2) -> Entity {
+    let definition = kind.definition();
+    let mut prop = commands.spawn((SpriteBundle {
+        texture: asset_server.load(definition.texture),
+        sprite: Sprite {
+            anchor: definition.anchor,
+            ..default()
+        },
+        transform: Transform {
+            translation: position.extend(definition.layer),
+            scale: Vec3::splat(PROP_SPRITE_SCALE),
+            ..default()
+        },
+        ..default()
+        },
+        definition.collider,
+        Prop(kind),
+    ));
+    if let Some(hit_points) = definition.hit_points {
+        prop.insert((Destructible, Health::new(hit_points)));
+    }
+    prop.id()
+}
@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    despawn_with_component,
+    enemy::{self, EnemyKilled},
+    loot::{self, PropBroken},
+    quality::QualityProfile,
+    GameState,
+};
+
+// This plugin throws a burst of sparks where an enemy dies and splinters where a prop breaks.
+// How many sparks can be on screen at once depends on the display quality.
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(FixedUpdate, (
+                spawn_death_bursts.after(enemy::despawn_dead_enemies),
+                spawn_break_bursts.after(loot::break_props),
+            ).run_if(in_state(GameState::Game)))
+            .add_systems(Update, update_particles.run_if(in_state(GameState::Game)))
+            .add_systems(OnEnter(GameState::Menu), despawn_with_component::<Particle>);
+    }
+}
+
+const PARTICLES_PER_DEATH: usize = 8;
+const PARTICLE_SIZE: f32 = 3.0;
+const PARTICLE_SPEED: f32 = 120.0;
+const PARTICLE_LIFETIME_SECONDS: f32 = 0.4;
+const PARTICLE_COLOR: Color = Color::rgb(3.0, 2.4, 0.8);
+const PARTICLES_PER_BREAK: usize = 12;
+const SPLINTER_COLOR: Color = Color::rgb(0.6, 0.35, 0.2);
+
+
+#[derive(Component)]
+pub struct Particle {
+    velocity: Vec2,
+    lifetime: Timer,
+}
+
+fn spawn_burst(commands: &mut Commands, rng: &mut impl Rng, position: Vec2, color: Color, count: usize) {
+    for _ in 0..count {
+        let direction = Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU));
+        let speed = PARTICLE_SPEED * rng.gen_range(0.5..1.0);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(PARTICLE_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(1.)),
+                ..default()
+            },
+            Particle {
+                velocity: direction * speed,
+                lifetime: Timer::from_seconds(PARTICLE_LIFETIME_SECONDS, TimerMode::Once),
+            },
+        ));
+    }
+}
+
+fn spawn_death_bursts(
+    mut commands: Commands,
+    profile: Res<QualityProfile>,
+    mut killed_events: EventReader<EnemyKilled>,
+    particle_query: Query<(), With<Particle>>,
+) {
+    // Sparks are cosmetic, so they don't draw from the seeded gameplay rng: the number spawned
+    // depends on the display quality and would change what a seed plays like
+    let mut rng = rand::thread_rng();
+    let mut budget = profile.max_particles.saturating_sub(particle_query.iter().count());
+    for killed in killed_events.read() {
+        let count = PARTICLES_PER_DEATH.min(budget);
+        budget -= count;
+        spawn_burst(&mut commands, &mut rng, killed.position, PARTICLE_COLOR, count);
+    }
+}
+
+fn spawn_break_bursts(
+    mut commands: Commands,
+    profile: Res<QualityProfile>,
+    mut broken_events: EventReader<PropBroken>,
+    particle_query: Query<(), With<Particle>>,
+) {
+    let mut rng = rand::thread_rng();
+    let mut budget = profile.max_particles.saturating_sub(particle_query.iter().count());
+    for broken in broken_events.read() {
+        let count = PARTICLES_PER_BREAK.min(budget);
+        budget -= count;
+        spawn_burst(&mut commands, &mut rng, broken.position, SPLINTER_COLOR, count);
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut particle_query: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    for (entity, mut particle, mut transform, mut sprite) in particle_query.iter_mut() {
+        particle.lifetime.tick(time.delta());
+        if particle.lifetime.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.);
+        sprite.color.set_a(particle.lifetime.percent_left());
+    }
+}
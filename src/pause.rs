@@ -0,0 +1,182 @@
+use bevy::prelude::*;
+
+use crate::menu::{MenuState, BACKGROUND, NORMAL_BUTTON};
+
+use super::{despawn_with_component, GameState, KeyBindings, TEXT_COLOR};
+
+// This plugin pauses a run with the pause keys, Escape or P by default. All gameplay runs in `FixedUpdate` gated on
+// `GameState::Game`, so simply leaving that state freezes it.
+// The pause screen hides itself while one of the settings screens is shown on top of it.
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, listen_for_pause.run_if(in_state(GameState::Game)))
+            .add_systems(Update, (listen_for_resume, pause_action).run_if(in_state(GameState::Pause)))
+            .add_systems(OnEnter(GameState::Pause), pause_menu_setup)
+            .add_systems(OnEnter(MenuState::Disabled), pause_menu_setup.run_if(in_state(GameState::Pause)))
+            .add_systems(OnExit(GameState::Pause), despawn_with_component::<OnPauseScreen>)
+            .add_systems(OnExit(MenuState::Disabled), despawn_with_component::<OnPauseScreen>);
+    }
+}
+
+const OVERLAY: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+
+// Tag component used to tag entities added on the pause screen
+#[derive(Component)]
+struct OnPauseScreen;
+
+// All actions that can be triggered from a button click on the pause screen
+#[derive(Component)]
+enum PauseButtonAction {
+    Resume,
+    Settings,
+    QuitToMenu,
+}
+
+fn pause_pressed(keyboard_input: &Input<KeyCode>, key_bindings: &KeyBindings) -> bool {
+    keyboard_input.any_just_pressed(key_bindings.pause.iter().copied())
+}
+
+fn listen_for_pause(
+    keyboard_input: Res<Input<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if pause_pressed(&keyboard_input, &key_bindings) {
+        game_state.set(GameState::Pause);
+    }
+}
+
+fn listen_for_resume(
+    keyboard_input: Res<Input<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    current_menu_state: Res<State<MenuState>>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if pause_pressed(&keyboard_input, &key_bindings) {
+        // Back out of the settings screens first
+        if *current_menu_state.get() != MenuState::Disabled {
+            menu_state.set(MenuState::Disabled);
+        } else {
+            game_state.set(GameState::Game);
+        }
+    }
+}
+
+fn pause_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let button_style = Style {
+        width: Val::Px(250.0),
+        height: Val::Px(65.0),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_icon_style = Style {
+        width: Val::Px(30.0),
+        height: Val::Auto,
+        position_type: PositionType::Absolute,
+        left: Val::Px(10.0),
+        right: Val::Auto,
+        top: Val::Auto,
+        bottom: Val::Auto,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font: font.clone(),
+        font_size: 40.0,
+        color: TEXT_COLOR,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: OVERLAY.into(),
+                ..default()
+            },
+            OnPauseScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: BACKGROUND.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(
+                        TextBundle::from_section(
+                            "Paused",
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 80.0,
+                                color: TEXT_COLOR,
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::all(Val::Px(50.0)),
+                            ..default()
+                        }),
+                    );
+
+                    for (action, icon, text) in [
+                        (PauseButtonAction::Resume, "textures/Game Icons/right.png", "Resume"),
+                        (PauseButtonAction::Settings, "textures/Game Icons/wrench.png", "Settings"),
+                        (PauseButtonAction::QuitToMenu, "textures/Game Icons/exitRight.png", "Quit to Menu"),
+                    ] {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: button_style.clone(),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                action,
+                            ))
+                            .with_children(|parent| {
+                                let icon = asset_server.load(icon);
+                                parent.spawn(ImageBundle {
+                                    style: button_icon_style.clone(),
+                                    image: UiImage::new(icon),
+                                    ..default()
+                                });
+                                parent.spawn(TextBundle::from_section(text, button_text_style.clone()));
+                            });
+                    }
+                });
+        });
+}
+
+fn pause_action(
+    interaction_query: Query<
+        (&Interaction, &PauseButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut menu_state: ResMut<NextState<MenuState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, pause_button_action) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            match pause_button_action {
+                PauseButtonAction::Resume => game_state.set(GameState::Game),
+                PauseButtonAction::Settings => menu_state.set(MenuState::Settings),
+                PauseButtonAction::QuitToMenu => game_state.set(GameState::Menu),
+            }
+        }
+    }
+}
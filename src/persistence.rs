@@ -0,0 +1,257 @@
+use bevy::{
+    prelude::*,
+    utils::thiserror::{self, Error},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    menu::{MenuState, MAX_VOLUME},
+    objective::RunProgress,
+    progression::PlayerLevel,
+    rng::GameSeed,
+    DisplayQuality, GameState, KeyBindings, Volume,
+};
+
+// This plugin keeps the settings, key bindings and best runs between sessions.
+// They are read once at startup and written back whenever the player leaves a settings
+// screen or finishes a run. A missing, unreadable or outdated save is replaced by defaults,
+// and so is any value in it the game has no setting for.
+pub struct PersistencePlugin;
+
+impl Plugin for PersistencePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<BestRuns>()
+            .add_systems(Startup, load_save)
+            .add_systems(OnExit(MenuState::SettingsDisplay), write_save)
+            .add_systems(OnExit(MenuState::SettingsSound), write_save)
+            .add_systems(OnEnter(GameState::GameWon), (record_run, write_save).chain())
+            .add_systems(OnEnter(GameState::GameLost), (record_run, write_save).chain());
+    }
+}
+
+// Bump this when the save format changes in a way old saves can't be read with
+const SAVE_VERSION: u32 = 1;
+const SAVE_NAME: &str = "lethal-lumens";
+const MAX_BEST_RUNS: usize = 10;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub seed: u64,
+    pub won: bool,
+    pub seconds: u64,
+    pub kills: u32,
+    pub level: u32,
+}
+
+impl RunRecord {
+    // Winning beats losing, then lasting longer, then killing more
+    fn rank(&self) -> (bool, u64, u32) {
+        (self.won, self.seconds, self.kills)
+    }
+}
+
+// Best runs so far, best first
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BestRuns(pub Vec<RunRecord>);
+
+impl BestRuns {
+    pub fn add(&mut self, record: RunRecord) {
+        self.0.push(record);
+        self.0.sort_by_key(|run| std::cmp::Reverse(run.rank()));
+        self.0.truncate(MAX_BEST_RUNS);
+    }
+}
+
+// Everything written to the save, fields missing from an older save keep their default
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveData {
+    pub version: u32,
+    pub display_quality: DisplayQuality,
+    pub volume: Volume,
+    pub key_bindings: KeyBindings,
+    pub best_runs: BestRuns,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        SaveData {
+            version: SAVE_VERSION,
+            display_quality: DisplayQuality::default(),
+            volume: Volume::default(),
+            key_bindings: KeyBindings::default(),
+            best_runs: BestRuns::default(),
+        }
+    }
+}
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("Could not access the save: {0}")]
+    Storage(String),
+    #[error("Could not access the save: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse the save: {0}")]
+    RonSpannedError(#[from] ron::error::SpannedError),
+    #[error("Could not write the save: {0}")]
+    RonError(#[from] ron::Error),
+    #[error("Save version {0} is not supported")]
+    Version(u32),
+}
+
+impl SaveData {
+    // Unknown enum variants and keys already fail to parse, this resets the values that
+    // parse fine but the game can't use, so a hand-edited save can't break the settings screens
+    fn validated(mut self) -> Self {
+        if self.volume.0 > MAX_VOLUME {
+            warn!("Saved volume {} is above {}, using the default volume", self.volume.0, MAX_VOLUME);
+            self.volume = Volume::default();
+        }
+        let defaults = KeyBindings::default();
+        let key_bindings = &mut self.key_bindings;
+        for (action, keys, default) in [
+            ("move up", &mut key_bindings.move_up, defaults.move_up),
+            ("move down", &mut key_bindings.move_down, defaults.move_down),
+            ("move left", &mut key_bindings.move_left, defaults.move_left),
+            ("move right", &mut key_bindings.move_right, defaults.move_right),
+            ("pause", &mut key_bindings.pause, defaults.pause),
+        ] {
+            if keys.is_empty() {
+                warn!("No saved key for {}, using the default keys", action);
+                *keys = default;
+            }
+        }
+        // Keep the best runs ordered and capped even if the file was edited
+        let runs = std::mem::take(&mut self.best_runs.0);
+        for run in runs {
+            self.best_runs.add(run);
+        }
+        self
+    }
+}
+
+pub fn parse_save(contents: &str) -> Result<SaveData, SaveError> {
+    let save: SaveData = ron::from_str(contents)?;
+    if save.version != SAVE_VERSION {
+        return Err(SaveError::Version(save.version));
+    }
+    Ok(save.validated())
+}
+
+fn load_save(
+    mut display_quality: ResMut<DisplayQuality>,
+    mut volume: ResMut<Volume>,
+    mut key_bindings: ResMut<KeyBindings>,
+    mut best_runs: ResMut<BestRuns>,
+) {
+    let save = match storage::read() {
+        Ok(Some(contents)) => parse_save(&contents).unwrap_or_else(|error| {
+            warn!("{}, using the default settings", error);
+            SaveData::default()
+        }),
+        // First launch
+        Ok(None) => SaveData::default(),
+        Err(error) => {
+            warn!("{}, using the default settings", error);
+            SaveData::default()
+        }
+    };
+    *display_quality = save.display_quality;
+    *volume = save.volume;
+    *key_bindings = save.key_bindings;
+    *best_runs = save.best_runs;
+}
+
+fn write_save(
+    display_quality: Res<DisplayQuality>,
+    volume: Res<Volume>,
+    key_bindings: Res<KeyBindings>,
+    best_runs: Res<BestRuns>,
+) {
+    let save = SaveData {
+        version: SAVE_VERSION,
+        display_quality: *display_quality,
+        volume: *volume,
+        key_bindings: key_bindings.clone(),
+        best_runs: best_runs.clone(),
+    };
+    let result = ron::ser::to_string_pretty(&save, ron::ser::PrettyConfig::default())
+        .map_err(SaveError::from)
+        .and_then(|contents| storage::write(&contents));
+    if let Err(error) = result {
+        warn!("{}", error);
+    }
+}
+
+fn record_run(
+    game_state: Res<State<GameState>>,
+    seed: Res<GameSeed>,
+    progress: Res<RunProgress>,
+    player_level: Res<PlayerLevel>,
+    mut best_runs: ResMut<BestRuns>,
+) {
+    best_runs.add(RunRecord {
+        seed: seed.current,
+        won: *game_state.get() == GameState::GameWon,
+        seconds: progress.elapsed.as_secs(),
+        kills: progress.kills,
+        level: player_level.level,
+    });
+}
+
+// The save is a file in the platform's config directory
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use std::{fs, io::ErrorKind, path::PathBuf};
+    use super::{SaveError, SAVE_NAME};
+
+    fn path() -> Result<PathBuf, SaveError> {
+        dirs::config_dir()
+            .map(|dir| dir.join(SAVE_NAME).join("save.ron"))
+            .ok_or_else(|| SaveError::Storage("no config directory".to_string()))
+    }
+
+    pub fn read() -> Result<Option<String>, SaveError> {
+        match fs::read_to_string(path()?) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn write(contents: &str) -> Result<(), SaveError> {
+        let path = path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+// The browser has no file system to write to, so the save goes to `localStorage`
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    use super::{SaveError, SAVE_NAME};
+
+    fn local_storage() -> Result<web_sys::Storage, SaveError> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| SaveError::Storage("localStorage is not available".to_string()))
+    }
+
+    pub fn read() -> Result<Option<String>, SaveError> {
+        local_storage()?
+            .get_item(SAVE_NAME)
+            .map_err(|error| SaveError::Storage(format!("{:?}", error)))
+    }
+
+    pub fn write(contents: &str) -> Result<(), SaveError> {
+        local_storage()?
+            .set_item(SAVE_NAME, contents)
+            .map_err(|error| SaveError::Storage(format!("{:?}", error)))
+    }
+}
@@ -5,17 +5,22 @@ use bevy::{
         tonemapping::Tonemapping,
     },
     prelude::*,
-    sprite::MaterialMesh2dBundle,
 };
 use rand::Rng;
-use crate::{game::{LEFT_WALL, RIGHT_WALL, TOP_WALL, BOTTOM_WALL}, GameState};
+use crate::{arena::ArenaBounds, GameState, KeyBindings};
 
-use super::health::Health;
+use super::{animation::SpriteAnimation, health::{Invulnerability, Shield}, hero::{self, HeroAssets, SelectedHero}, light::{self, LightAuraGlowAssets}, obstacle::{self, ColliderQuery}};
 
 pub const PLAYER_SIZE: f32 = 20.0;
-const PLAYER_SPEED: f32 = 500.0;
-const PLAYER_COLOR: Color = Color::rgb(0.3, 0.3, 10.7);
-const PLAYER_INITIAL_HIT_POINTS: u32 = 100;
+pub const PLAYER_SPEED: f32 = 500.0;
+const PLAYER_SPRITE_SCALE: f32 = 2.0;
+pub const PLAYER_INITIAL_HIT_POINTS: u32 = 100;
+const PLAYER_PICKUP_RADIUS: f32 = 100.0;
+// After getting hit, the player can't be hurt again for this long
+pub const PLAYER_INVULNERABILITY_SECONDS: f32 = 0.5;
+// The player starts without a shield, upgrades add to it
+const PLAYER_SHIELD_RECHARGE_RATE: f32 = 10.0;
+const PLAYER_SHIELD_RECHARGE_DELAY_SECONDS: f32 = 3.0;
 
 
 pub struct PlayerPlugin;
@@ -23,7 +28,10 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app
-        .add_systems(OnEnter(GameState::Game), spawn_player)
+        .init_resource::<SelectedHero>()
+        .add_systems(Startup, hero::load_hero_assets)
+        // A run starts when leaving the menu, so resuming from the pause screen doesn't spawn a second player
+        .add_systems(OnExit(GameState::Menu), spawn_player)
         .add_systems(FixedUpdate, move_player.run_if(in_state(GameState::Game)));
     }
 }
@@ -32,60 +40,103 @@ impl Plugin for PlayerPlugin {
 #[derive(Component)]
 pub struct Player;
 
+// Everything about the player that upgrades can improve during a run
+#[derive(Component)]
+pub struct PlayerStats {
+    pub speed: f32,
+    // Distance from which experience gems start flying towards the player
+    pub pickup_radius: f32,
+}
+
+impl Default for PlayerStats {
+    fn default() -> Self {
+        PlayerStats {
+            speed: PLAYER_SPEED,
+            pickup_radius: PLAYER_PICKUP_RADIUS,
+        }
+    }
+}
+
 
 fn spawn_player(mut commands: Commands, 
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>) {
-    commands.spawn((MaterialMesh2dBundle {
-        mesh: meshes.add(shape::Circle::new(PLAYER_SIZE).into()).into(),
-        material: materials.add(ColorMaterial::from(PLAYER_COLOR)),
+    selected_hero: Res<SelectedHero>,
+    hero_assets: Res<HeroAssets>,
+    glow_assets: Res<LightAuraGlowAssets>) {
+    let hero = selected_hero.0.definition();
+    let (stats, health, aura) = hero.starting_stats();
+    let light_radius = aura.radius;
+    commands.spawn((SpriteSheetBundle {
+        texture_atlas: hero_assets.atlas(selected_hero.0),
+        sprite: TextureAtlasSprite {
+            custom_size: Some(hero.frame_size * PLAYER_SPRITE_SCALE),
+            ..default()
+        },
         transform: Transform::from_translation(Vec3::new(-200., 0., 0.)),
         ..default()
         }, 
         Player,
-        Health {hit_points: PLAYER_INITIAL_HIT_POINTS}
-    ));
+        selected_hero.0,
+        health,
+        Shield::new(0, PLAYER_SHIELD_RECHARGE_RATE, PLAYER_SHIELD_RECHARGE_DELAY_SECONDS),
+        Invulnerability::new(PLAYER_INVULNERABILITY_SECONDS),
+        stats,
+        aura,
+        SpriteAnimation::new(hero.clips),
+    )).with_children(|parent| {
+        parent.spawn(light::light_aura_glow(&glow_assets, light_radius));
+    });
 }
 
 fn move_player(
     keyboard_input: Res<Input<KeyCode>>,
-    mut query: Query<&mut Transform, With<Player>>,
+    key_bindings: Res<KeyBindings>,
+    bounds: Res<ArenaBounds>,
+    mut query: Query<(&mut Transform, &PlayerStats, &mut SpriteAnimation), With<Player>>,
+    obstacle_query: ColliderQuery,
     time: Res<Time>,
 ) {
-    let mut player_transform = query.single_mut();
+    let (mut player_transform, stats, mut animation) = query.single_mut();
     let mut direction_x = 0.0;
     let mut direction_y = 0.0;
 
-    if keyboard_input.pressed(KeyCode::A) {
+    if keyboard_input.any_pressed(key_bindings.move_left.iter().copied()) {
         direction_x -= 1.0;
     }
 
-    if keyboard_input.pressed(KeyCode::D) {
+    if keyboard_input.any_pressed(key_bindings.move_right.iter().copied()) {
         direction_x += 1.0;
     }
 
-    if keyboard_input.pressed(KeyCode::S) {
+    if keyboard_input.any_pressed(key_bindings.move_down.iter().copied()) {
         direction_y -= 1.0;
     }
 
-    if keyboard_input.pressed(KeyCode::W) {
+    if keyboard_input.any_pressed(key_bindings.move_up.iter().copied()) {
         direction_y += 1.0;
     }
 
     // Calculate the new position based on player input
     let new_player_position_x =
-        player_transform.translation.x + direction_x * PLAYER_SPEED * time.delta_seconds();
+        player_transform.translation.x + direction_x * stats.speed * time.delta_seconds();
     let new_player_position_y =
-        player_transform.translation.y + direction_y * PLAYER_SPEED * time.delta_seconds();
+        player_transform.translation.y + direction_y * stats.speed * time.delta_seconds();
 
     // // Update the player position,
     // // making sure it doesn't cause the player to leave the arena
-    let left_bound = LEFT_WALL + PLAYER_SIZE / 2.0;
-    let right_bound = RIGHT_WALL - PLAYER_SIZE / 2.0;
-    let top_bound = TOP_WALL - PLAYER_SIZE / 2.0;
-    let bottom_bound = BOTTOM_WALL + PLAYER_SIZE / 2.0;
+    let bounds = bounds.shrink(PLAYER_SIZE / 2.0);
+    let left_bound = bounds.left;
+    let right_bound = bounds.right;
+    let top_bound = bounds.top;
+    let bottom_bound = bounds.bottom;
+
+    let new_player_position = Vec2::new(
+        new_player_position_x.clamp(left_bound, right_bound),
+        new_player_position_y.clamp(bottom_bound, top_bound),
+    );
+    // Walking into an obstacle slides along it
+    let new_player_position = obstacle::resolve_collisions(new_player_position, PLAYER_SIZE / 2.0, &obstacle::colliders(&obstacle_query));
+    player_transform.translation.x = new_player_position.x;
+    player_transform.translation.y = new_player_position.y;
+    animation.set_movement(Vec2::new(direction_x, direction_y));
 
-    player_transform.translation.x = new_player_position_x.clamp(left_bound, right_bound);
-    player_transform.translation.y = new_player_position_y.clamp(bottom_bound, top_bound);
-
-}
\ No newline at end of file
+}
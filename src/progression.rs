@@ -0,0 +1,398 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use rand::seq::SliceRandom;
+use crate::{
+    despawn_with_component,
+    enemy::{self, EnemyKilled},
+    health::{Health, Shield},
+    light::LightAura,
+    menu::{BACKGROUND, NORMAL_BUTTON},
+    player::{self, Player, PlayerStats},
+    rng::GameRng,
+    GameState, TEXT_COLOR,
+};
+
+// This plugin handles experience and leveling: killed enemies drop gems that fly to the player,
+// and every level gained stops the run until one of three random upgrades is picked
+pub struct ProgressionPlugin;
+
+impl Plugin for ProgressionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<PlayerLevel>()
+        .add_systems(Startup, load_xp_gem_assets)
+        .add_systems(OnExit(GameState::Menu), (reset_player_level, xp_bar_setup))
+        .add_systems(OnEnter(GameState::Menu), (
+            despawn_with_component::<XpGem>,
+            despawn_with_component::<OnXpBar>,
+        ))
+        .add_systems(FixedUpdate, (
+            drop_xp_gems.after(enemy::despawn_dead_enemies),
+            collect_xp_gems,
+        ).chain().run_if(in_state(GameState::Game)))
+        .add_systems(Update, update_xp_bar.run_if(in_state(GameState::Game)))
+        .add_systems(OnEnter(GameState::LevelUp), level_up_setup)
+        .add_systems(OnExit(GameState::LevelUp), despawn_with_component::<OnLevelUpScreen>)
+        .add_systems(Update, level_up_action.run_if(in_state(GameState::LevelUp)));
+    }
+}
+
+const XP_GEM_SIZE: f32 = 4.0;
+const XP_GEM_COLOR: Color = Color::rgb(0.3, 4.0, 6.0);
+const XP_GEM_SPEED: f32 = 600.0;
+const XP_BAR_COLOR: Color = Color::rgb(0.3, 0.7, 0.9);
+const XP_BAR_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+const UPGRADE_CHOICES: usize = 3;
+const LIGHT_RADIUS_UPGRADE: f32 = 1.15;
+const LIGHT_DAMAGE_UPGRADE: u32 = 10;
+const MOVE_SPEED_UPGRADE: f32 = 1.1;
+const MAX_HEALTH_UPGRADE: u32 = 20;
+const REGENERATION_UPGRADE: f32 = 1.0;
+const SHIELD_UPGRADE: u32 = 25;
+
+
+#[derive(Resource, Debug)]
+pub struct PlayerLevel {
+    pub level: u32,
+    // Experience collected towards the next level
+    pub xp: u32,
+}
+
+impl Default for PlayerLevel {
+    fn default() -> Self {
+        PlayerLevel { level: 1, xp: 0 }
+    }
+}
+
+impl PlayerLevel {
+    pub fn xp_to_next_level(&self) -> u32 {
+        5 + self.level * 5
+    }
+}
+
+#[derive(Component)]
+pub struct XpGem {
+    pub value: u32
+}
+
+// Mesh and material shared by every experience gem, made once at startup
+#[derive(Resource)]
+pub struct XpGemAssets {
+    mesh: Mesh2dHandle,
+    material: Handle<ColorMaterial>,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Upgrade {
+    LightRadius,
+    LightDamage,
+    MoveSpeed,
+    MaxHealth,
+    Regeneration,
+    Shield,
+}
+
+impl Upgrade {
+    const ALL: [Upgrade; 6] = [
+        Upgrade::LightRadius,
+        Upgrade::LightDamage,
+        Upgrade::MoveSpeed,
+        Upgrade::MaxHealth,
+        Upgrade::Regeneration,
+        Upgrade::Shield,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Upgrade::LightRadius => "Wider Glow",
+            Upgrade::LightDamage => "Brighter Glow",
+            Upgrade::MoveSpeed => "Swift Feet",
+            Upgrade::MaxHealth => "Tough Skin",
+            Upgrade::Regeneration => "Second Wind",
+            Upgrade::Shield => "Light Ward",
+        }
+    }
+
+    fn description(self) -> String {
+        match self {
+            Upgrade::LightRadius => format!("Light radius +{:.0}%", (LIGHT_RADIUS_UPGRADE - 1.0) * 100.0),
+            Upgrade::LightDamage => format!("Light damage +{}", LIGHT_DAMAGE_UPGRADE),
+            Upgrade::MoveSpeed => format!("Move speed +{:.0}%", (MOVE_SPEED_UPGRADE - 1.0) * 100.0),
+            Upgrade::MaxHealth => format!("Max HP +{}", MAX_HEALTH_UPGRADE),
+            Upgrade::Regeneration => format!("Regenerate {} HP/s", REGENERATION_UPGRADE),
+            Upgrade::Shield => format!("Shield +{}", SHIELD_UPGRADE),
+        }
+    }
+
+    fn apply(self, aura: &mut LightAura, stats: &mut PlayerStats, health: &mut Health, shield: &mut Shield) {
+        match self {
+            Upgrade::LightRadius => aura.radius *= LIGHT_RADIUS_UPGRADE,
+            Upgrade::LightDamage => aura.damage += LIGHT_DAMAGE_UPGRADE,
+            Upgrade::MoveSpeed => stats.speed *= MOVE_SPEED_UPGRADE,
+            Upgrade::MaxHealth => {
+                health.max_hit_points += MAX_HEALTH_UPGRADE;
+                health.hit_points += MAX_HEALTH_UPGRADE;
+            }
+            Upgrade::Regeneration => health.regeneration += REGENERATION_UPGRADE,
+            Upgrade::Shield => {
+                shield.max_points += SHIELD_UPGRADE;
+                shield.points += SHIELD_UPGRADE;
+            }
+        }
+    }
+}
+
+// Tag component used to tag entities added for the experience bar
+#[derive(Component)]
+struct OnXpBar;
+
+#[derive(Component)]
+struct XpBarFill;
+
+// Tag component used to tag entities added on the level up screen
+#[derive(Component)]
+struct OnLevelUpScreen;
+
+
+fn load_xp_gem_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(XpGemAssets {
+        mesh: meshes.add(shape::RegularPolygon::new(XP_GEM_SIZE, 4).into()).into(),
+        material: materials.add(ColorMaterial::from(XP_GEM_COLOR)),
+    });
+}
+
+fn reset_player_level(mut player_level: ResMut<PlayerLevel>) {
+    *player_level = PlayerLevel::default();
+}
+
+fn drop_xp_gems(
+    mut commands: Commands,
+    gem_assets: Res<XpGemAssets>,
+    mut killed_events: EventReader<EnemyKilled>,
+) {
+    for killed in killed_events.read() {
+        spawn_xp_gem(&mut commands, &gem_assets, killed.kind.archetype().xp, killed.position);
+    }
+}
+
+pub fn spawn_xp_gem(
+    commands: &mut Commands,
+    gem_assets: &XpGemAssets,
+    value: u32,
+    position: Vec2,
+) {
+    commands.spawn((MaterialMesh2dBundle {
+        mesh: gem_assets.mesh.clone(),
+        material: gem_assets.material.clone(),
+        transform: Transform::from_translation(position.extend(-0.5)),
+        ..default()
+        },
+        XpGem { value },
+    ));
+}
+
+fn collect_xp_gems(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut player_level: ResMut<PlayerLevel>,
+    mut game_state: ResMut<NextState<GameState>>,
+    player_query: Query<(&Transform, &PlayerStats), With<Player>>,
+    mut gem_query: Query<(Entity, &mut Transform, &XpGem), Without<Player>>,
+) {
+    let (player_transform, stats) = player_query.single();
+    let player_position = player_transform.translation.truncate();
+
+    for (gem_entity, mut gem_transform, gem) in gem_query.iter_mut() {
+        let to_player = player_position - gem_transform.translation.truncate();
+        let distance = to_player.length();
+        if distance < player::PLAYER_SIZE {
+            commands.entity(gem_entity).despawn();
+            player_level.xp += gem.value;
+        } else if distance < stats.pickup_radius {
+            let step = to_player.normalize() * (XP_GEM_SPEED * time.delta_seconds()).min(distance);
+            gem_transform.translation += step.extend(0.);
+        }
+    }
+
+    // One level at a time, anything left over levels up again once the upgrade is picked
+    let xp_to_next_level = player_level.xp_to_next_level();
+    if player_level.xp >= xp_to_next_level {
+        player_level.xp -= xp_to_next_level;
+        player_level.level += 1;
+        game_state.set(GameState::LevelUp);
+    }
+}
+
+fn xp_bar_setup(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Px(8.0),
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(0.0),
+                    ..default()
+                },
+                background_color: XP_BAR_BACKGROUND.into(),
+                ..default()
+            },
+            OnXpBar,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: XP_BAR_COLOR.into(),
+                    ..default()
+                },
+                XpBarFill,
+            ));
+        });
+}
+
+fn update_xp_bar(
+    player_level: Res<PlayerLevel>,
+    mut query: Query<&mut Style, With<XpBarFill>>,
+) {
+    let fraction = player_level.xp as f32 / player_level.xp_to_next_level() as f32;
+    for mut style in query.iter_mut() {
+        style.width = Val::Percent(fraction.min(1.0) * 100.0);
+    }
+}
+
+fn level_up_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    player_level: Res<PlayerLevel>,
+    mut rng: ResMut<GameRng>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let choices: Vec<Upgrade> = Upgrade::ALL
+        .choose_multiple(&mut rng.0, UPGRADE_CHOICES)
+        .copied()
+        .collect();
+
+    let card_style = Style {
+        width: Val::Px(220.0),
+        height: Val::Px(160.0),
+        margin: UiRect::all(Val::Px(15.0)),
+        padding: UiRect::all(Val::Px(10.0)),
+        flex_direction: FlexDirection::Column,
+        justify_content: JustifyContent::SpaceEvenly,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..default()
+            },
+            OnLevelUpScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: BACKGROUND.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(
+                        TextBundle::from_section(
+                            format!("Level {}!", player_level.level),
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 60.0,
+                                color: TEXT_COLOR,
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::all(Val::Px(30.0)),
+                            ..default()
+                        }),
+                    );
+
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for upgrade in choices {
+                                parent
+                                    .spawn((
+                                        ButtonBundle {
+                                            style: card_style.clone(),
+                                            background_color: NORMAL_BUTTON.into(),
+                                            ..default()
+                                        },
+                                        upgrade,
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section(
+                                            upgrade.name(),
+                                            TextStyle {
+                                                font: font.clone(),
+                                                font_size: 30.0,
+                                                color: TEXT_COLOR,
+                                            },
+                                        ));
+                                        parent.spawn(TextBundle::from_section(
+                                            upgrade.description(),
+                                            TextStyle {
+                                                font: font.clone(),
+                                                font_size: 20.0,
+                                                color: TEXT_COLOR,
+                                            },
+                                        ));
+                                    });
+                            }
+                        });
+                });
+        });
+}
+
+fn level_up_action(
+    interaction_query: Query<
+        (&Interaction, &Upgrade),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut player_query: Query<(&mut LightAura, &mut PlayerStats, &mut Health, &mut Shield), With<Player>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, upgrade) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            let (mut aura, mut stats, mut health, mut shield) = player_query.single_mut();
+            upgrade.apply(&mut aura, &mut stats, &mut health, &mut shield);
+            game_state.set(GameState::Game);
+            return;
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use bevy::{
+    core_pipeline::{bloom::BloomSettings, tonemapping::Tonemapping},
+    prelude::*,
+};
+
+use crate::{animation::SpriteAnimation, enemy::Enemy, DisplayQuality};
+
+// This plugin turns the display quality setting into concrete rendering choices.
+// Everything is applied again as soon as the setting changes, so the settings screen
+// takes effect without restarting, even in the middle of a run.
+pub struct QualityPlugin;
+
+impl Plugin for QualityPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<QualityProfile>()
+            .add_systems(Update, (
+                update_quality_profile.run_if(resource_exists_and_changed::<DisplayQuality>()),
+                (apply_camera_quality, apply_msaa, apply_enemy_glow),
+            ).chain());
+    }
+}
+
+// Sprites are tinted above 1.0 so the bloom makes them shine
+const ENEMY_GLOW_COLOR: Color = Color::rgb(1.4, 1.3, 1.2);
+
+
+// What a display quality level means for rendering
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct QualityProfile {
+    // Render to a high dynamic range target. Bloom needs it.
+    pub hdr: bool,
+    // Bloom intensity, `None` turns bloom off
+    pub bloom: Option<f32>,
+    pub tonemapping: Tonemapping,
+    pub msaa: Msaa,
+    // Most particles alive at the same time, new ones are dropped past this
+    pub max_particles: usize,
+    pub enemy_glow: bool,
+}
+
+impl QualityProfile {
+    pub fn for_quality(quality: DisplayQuality) -> Self {
+        match quality {
+            DisplayQuality::Low => QualityProfile {
+                hdr: false,
+                bloom: None,
+                tonemapping: Tonemapping::None,
+                msaa: Msaa::Off,
+                max_particles: 50,
+                enemy_glow: false,
+            },
+            DisplayQuality::Medium => QualityProfile {
+                hdr: true,
+                bloom: Some(BloomSettings::NATURAL.intensity),
+                tonemapping: Tonemapping::TonyMcMapface,
+                msaa: Msaa::Sample4,
+                max_particles: 300,
+                enemy_glow: false,
+            },
+            DisplayQuality::High => QualityProfile {
+                hdr: true,
+                bloom: Some(0.3),
+                tonemapping: Tonemapping::TonyMcMapface,
+                msaa: Msaa::Sample4,
+                max_particles: 1000,
+                enemy_glow: true,
+            },
+        }
+    }
+}
+
+impl Default for QualityProfile {
+    fn default() -> Self {
+        QualityProfile::for_quality(DisplayQuality::Medium)
+    }
+}
+
+fn update_quality_profile(display_quality: Res<DisplayQuality>, mut profile: ResMut<QualityProfile>) {
+    profile.set_if_neq(QualityProfile::for_quality(*display_quality));
+}
+
+// Also picks up cameras spawned after the setting was chosen
+fn apply_camera_quality(
+    mut commands: Commands,
+    profile: Res<QualityProfile>,
+    mut camera_query: Query<(Entity, &mut Camera, &mut Tonemapping)>,
+) {
+    for (entity, mut camera, mut tonemapping) in camera_query.iter_mut() {
+        if !profile.is_changed() && !camera.is_added() {
+            continue;
+        }
+        // Changing the camera rebuilds its render targets, so leave it alone when it matches
+        if camera.hdr != profile.hdr {
+            camera.hdr = profile.hdr;
+        }
+        tonemapping.set_if_neq(profile.tonemapping);
+        match profile.bloom {
+            Some(intensity) => {
+                commands.entity(entity).insert(BloomSettings { intensity, ..BloomSettings::NATURAL });
+            }
+            None => {
+                commands.entity(entity).remove::<BloomSettings>();
+            }
+        }
+    }
+}
+
+fn apply_msaa(profile: Res<QualityProfile>, mut msaa: ResMut<Msaa>) {
+    if profile.is_changed() {
+        msaa.set_if_neq(profile.msaa);
+    }
+}
+
+fn apply_enemy_glow(
+    profile: Res<QualityProfile>,
+    mut enemy_query: Query<(Ref<Enemy>, &mut SpriteAnimation)>,
+) {
+    let color = if profile.enemy_glow { ENEMY_GLOW_COLOR } else { Color::WHITE };
+    for (enemy, mut animation) in enemy_query.iter_mut() {
+        if profile.is_changed() || enemy.is_added() {
+            animation.base_color = color;
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use crate::GameState;
+
+// This plugin owns the random number generator every gameplay system draws from.
+// It is seeded again at the start of each run, so a run can be replayed from its seed, either
+// with `--seed` or with "Replay Seed" on the main menu.
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<GameSeed>()
+        .insert_resource(GameRng(StdRng::seed_from_u64(0)))
+        .add_systems(OnExit(GameState::Menu), seed_run);
+    }
+}
+
+const SEED_ARG: &str = "--seed";
+
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng(pub StdRng);
+
+#[derive(Resource, Default, Debug)]
+pub struct GameSeed {
+    // Seed to use for every run instead of a random one
+    pub requested: Option<u64>,
+    // Seed of the current (or last) run
+    pub current: u64,
+    // Seed of the last run started this session, if there was one
+    pub last: Option<u64>,
+    // Set by the menu to play the last seed again instead of picking a new one
+    pub replay: bool,
+}
+
+impl GameSeed {
+    // Reads `--seed <number>` from the command line
+    pub fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        let requested = args
+            .iter()
+            .position(|arg| arg == SEED_ARG)
+            .and_then(|index| args.get(index + 1))
+            .and_then(|seed| match seed.parse() {
+                Ok(seed) => Some(seed),
+                Err(_) => {
+                    warn!("Ignoring invalid seed {:?}", seed);
+                    None
+                }
+            });
+        GameSeed { requested, ..default() }
+    }
+}
+
+pub fn seed_run(mut seed: ResMut<GameSeed>, mut rng: ResMut<GameRng>) {
+    seed.current = match seed.last {
+        Some(last) if seed.replay => last,
+        _ => seed.requested.unwrap_or_else(|| rand::thread_rng().gen()),
+    };
+    seed.replay = false;
+    seed.last = Some(seed.current);
+    rng.0 = StdRng::seed_from_u64(seed.current);
+    info!("Starting run with seed {}", seed.current);
+}
@@ -0,0 +1,15 @@
+use bevy::prelude::*;
+
+use crate::{DisplayQuality, Volume};
+
+// This plugin holds the options picked on the settings screens.
+// They are applied by the `quality` and `sound` modules, and saved by the `persistence` module.
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<DisplayQuality>()
+            .init_resource::<Volume>();
+    }
+}
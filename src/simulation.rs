@@ -0,0 +1,40 @@
+use bevy::{
+    asset::AssetPlugin,
+    input::InputPlugin,
+    prelude::*,
+    render::view::Msaa,
+    text::Font,
+    time::TimeUpdateStrategy,
+};
+
+// Runs the gameplay plugins without a window, renderer or audio device, e.g. for tests on CI:
+//
+//     App::new().add_plugins((HeadlessPlugin, GamePlugin))
+//
+// Every `App::update` advances time by exactly one fixed timestep, so each update runs
+// `FixedUpdate` once and a test can step the game tick by tick.
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        let timestep = Time::<Fixed>::default().timestep();
+        app
+        .add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            InputPlugin,
+            TransformPlugin,
+            HierarchyPlugin,
+        ))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(timestep))
+        // Asset types and resources normally registered by the rendering plugins. Nothing gets drawn,
+        // but gameplay code still creates handles to them.
+        .init_asset::<Image>()
+        .init_asset::<Mesh>()
+        .init_asset::<ColorMaterial>()
+        .init_asset::<TextureAtlas>()
+        .init_asset::<Font>()
+        .init_resource::<Msaa>();
+    }
+}
+
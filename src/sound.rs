@@ -0,0 +1,263 @@
+use bevy::prelude::*;
+#[cfg(feature = "audio")]
+use bevy::audio::{PlaybackMode, Volume as PlaybackVolume};
+
+use crate::{
+    enemy::EnemyKilled,
+    health::Damaged,
+    player::Player,
+    GameState,
+};
+#[cfg(feature = "audio")]
+use crate::{boss::Boss, menu::MAX_VOLUME, Volume};
+
+// This plugin plays the background music for the current state and the sound effects.
+// Everything goes through two channels, music and effects, both scaled by the `Volume`
+// setting. Changing the volume also changes the music that is already playing.
+// Built without the `audio` feature, e.g. for the headless tests, effects are still sent but
+// nothing plays them.
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<SoundEffect>()
+            .init_resource::<AudioChannels>()
+            .add_systems(OnEnter(GameState::LevelUp), play_level_up)
+            .add_systems(Update, (detect_player_hit, detect_enemy_deaths).run_if(in_state(GameState::Game)));
+        #[cfg(feature = "audio")]
+        app
+            .init_resource::<CurrentMusic>()
+            .add_systems(Startup, load_sounds)
+            .add_systems(Update, (
+                switch_music,
+                update_music_volume.run_if(resource_changed::<Volume>().or_else(resource_changed::<AudioChannels>())),
+                play_sound_effects.after(detect_player_hit).after(detect_enemy_deaths),
+            ).chain());
+    }
+}
+
+
+// Loudness of each channel before the master volume is applied
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct AudioChannels {
+    pub music: f32,
+    pub effects: f32,
+}
+
+impl Default for AudioChannels {
+    fn default() -> Self {
+        AudioChannels { music: 0.5, effects: 0.8 }
+    }
+}
+
+#[cfg(feature = "audio")]
+impl AudioChannels {
+    fn music_volume(&self, master: Volume) -> f32 {
+        self.music * master.0 as f32 / MAX_VOLUME as f32
+    }
+
+    fn effects_volume(&self, master: Volume) -> f32 {
+        self.effects * master.0 as f32 / MAX_VOLUME as f32
+    }
+}
+
+// Send this event to play a sound effect, the same effect plays at most once per frame
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundEffect {
+    PlayerHit,
+    EnemyDeath,
+    LevelUp,
+    ButtonClick,
+}
+
+#[cfg(feature = "audio")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MusicTrack {
+    Menu,
+    Game,
+    Boss,
+    Won,
+    Lost,
+}
+
+#[cfg(feature = "audio")]
+impl MusicTrack {
+    // The win and lose stingers play once, the rest loops
+    fn mode(self) -> PlaybackMode {
+        match self {
+            MusicTrack::Won | MusicTrack::Lost => PlaybackMode::Despawn,
+            _ => PlaybackMode::Loop,
+        }
+    }
+}
+
+#[cfg(feature = "audio")]
+#[derive(Resource)]
+struct SoundAssets {
+    menu_music: Handle<AudioSource>,
+    game_music: Handle<AudioSource>,
+    boss_music: Handle<AudioSource>,
+    won_stinger: Handle<AudioSource>,
+    lost_stinger: Handle<AudioSource>,
+    player_hit: Handle<AudioSource>,
+    enemy_death: Handle<AudioSource>,
+    level_up: Handle<AudioSource>,
+    button_click: Handle<AudioSource>,
+}
+
+#[cfg(feature = "audio")]
+impl SoundAssets {
+    fn music(&self, track: MusicTrack) -> Handle<AudioSource> {
+        match track {
+            MusicTrack::Menu => self.menu_music.clone(),
+            MusicTrack::Game => self.game_music.clone(),
+            MusicTrack::Boss => self.boss_music.clone(),
+            MusicTrack::Won => self.won_stinger.clone(),
+            MusicTrack::Lost => self.lost_stinger.clone(),
+        }
+    }
+
+    fn effect(&self, effect: SoundEffect) -> Handle<AudioSource> {
+        match effect {
+            SoundEffect::PlayerHit => self.player_hit.clone(),
+            SoundEffect::EnemyDeath => self.enemy_death.clone(),
+            SoundEffect::LevelUp => self.level_up.clone(),
+            SoundEffect::ButtonClick => self.button_click.clone(),
+        }
+    }
+}
+
+// Which track is playing, and the entity playing it
+#[cfg(feature = "audio")]
+#[derive(Resource, Default)]
+struct CurrentMusic(Option<(MusicTrack, Entity)>);
+
+// Tag component used to tag the entity playing the music
+#[cfg(feature = "audio")]
+#[derive(Component)]
+struct MusicPlayer;
+
+
+#[cfg(feature = "audio")]
+fn load_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SoundAssets {
+        menu_music: asset_server.load("audio/music_menu.wav"),
+        game_music: asset_server.load("audio/music_game.wav"),
+        boss_music: asset_server.load("audio/music_boss.wav"),
+        won_stinger: asset_server.load("audio/stinger_win.wav"),
+        lost_stinger: asset_server.load("audio/stinger_lose.wav"),
+        player_hit: asset_server.load("audio/sfx_player_hit.wav"),
+        enemy_death: asset_server.load("audio/sfx_enemy_death.wav"),
+        level_up: asset_server.load("audio/sfx_level_up.wav"),
+        button_click: asset_server.load("audio/sfx_click.wav"),
+    });
+}
+
+#[cfg(feature = "audio")]
+fn switch_music(
+    mut commands: Commands,
+    game_state: Res<State<GameState>>,
+    sounds: Res<SoundAssets>,
+    channels: Res<AudioChannels>,
+    volume: Res<Volume>,
+    mut current: ResMut<CurrentMusic>,
+    boss_query: Query<(), With<Boss>>,
+) {
+    let wanted = match game_state.get() {
+        GameState::Splash => None,
+        GameState::Menu => Some(MusicTrack::Menu),
+        // The music keeps going on the pause and level up screens
+        GameState::Game | GameState::Pause | GameState::LevelUp => {
+            if boss_query.is_empty() {
+                Some(MusicTrack::Game)
+            } else {
+                Some(MusicTrack::Boss)
+            }
+        }
+        GameState::GameWon => Some(MusicTrack::Won),
+        GameState::GameLost => Some(MusicTrack::Lost),
+    };
+    if current.0.map(|(track, _)| track) == wanted {
+        return;
+    }
+
+    if let Some((_, entity)) = current.0.take() {
+        // A stinger despawns itself once it is over
+        if let Some(entity) = commands.get_entity(entity) {
+            entity.despawn_recursive();
+        }
+    }
+    if let Some(track) = wanted {
+        let entity = commands
+            .spawn((
+                AudioBundle {
+                    source: sounds.music(track),
+                    settings: PlaybackSettings {
+                        mode: track.mode(),
+                        volume: PlaybackVolume::new_absolute(channels.music_volume(*volume)),
+                        ..default()
+                    },
+                },
+                MusicPlayer,
+            ))
+            .id();
+        current.0 = Some((track, entity));
+    }
+}
+
+#[cfg(feature = "audio")]
+fn update_music_volume(
+    channels: Res<AudioChannels>,
+    volume: Res<Volume>,
+    music_query: Query<&AudioSink, With<MusicPlayer>>,
+) {
+    for sink in music_query.iter() {
+        sink.set_volume(channels.music_volume(*volume));
+    }
+}
+
+fn play_level_up(mut sound_effects: EventWriter<SoundEffect>) {
+    sound_effects.send(SoundEffect::LevelUp);
+}
+
+fn detect_player_hit(
+    mut damaged_events: EventReader<Damaged>,
+    player_query: Query<(), With<Player>>,
+    mut sound_effects: EventWriter<SoundEffect>,
+) {
+    if damaged_events.read().any(|damaged| player_query.contains(damaged.entity)) {
+        sound_effects.send(SoundEffect::PlayerHit);
+    }
+}
+
+fn detect_enemy_deaths(
+    mut killed_events: EventReader<EnemyKilled>,
+    mut sound_effects: EventWriter<SoundEffect>,
+) {
+    if killed_events.read().count() > 0 {
+        sound_effects.send(SoundEffect::EnemyDeath);
+    }
+}
+
+#[cfg(feature = "audio")]
+fn play_sound_effects(
+    mut commands: Commands,
+    sounds: Res<SoundAssets>,
+    channels: Res<AudioChannels>,
+    volume: Res<Volume>,
+    mut sound_effects: EventReader<SoundEffect>,
+) {
+    let mut played: Vec<SoundEffect> = Vec::new();
+    for effect in sound_effects.read() {
+        if played.contains(effect) {
+            continue;
+        }
+        played.push(*effect);
+        commands.spawn(AudioBundle {
+            source: sounds.effect(*effect),
+            settings: PlaybackSettings::DESPAWN
+                .with_volume(PlaybackVolume::new_absolute(channels.effects_volume(*volume))),
+        });
+    }
+}
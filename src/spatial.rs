@@ -0,0 +1,145 @@
+use bevy::{prelude::*, utils::HashMap};
+use crate::{
+    enemy::{self, Burrowed, Enemy},
+    GameState,
+};
+
+// This plugin keeps every enemy above ground in a uniform grid, so finding the ones close to a
+// point only looks at a few cells instead of every enemy. It is rebuilt each fixed tick right
+// after enemies move, anything reading it before that sees where they were a tick ago.
+// Enemies spawned since are added just before they move, so they steer around each other
+// from their first tick instead of stacking up for one.
+pub struct SpatialPlugin;
+
+impl Plugin for SpatialPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<SpatialGrid>()
+        .add_systems(FixedUpdate, (
+            index_spawned_enemies
+                .after(enemy::despawn_dead_enemies)
+                .before(enemy::move_enemy),
+            index_enemies
+                .after(enemy::move_enemy)
+                .before(enemy::enemy_damage_player),
+        ).run_if(in_state(GameState::Game)))
+        .add_systems(OnEnter(GameState::Menu), reset_grid);
+    }
+}
+
+// A few times the size of a regular enemy, so most lookups only touch the cells around a point
+const CELL_SIZE: f32 = 64.0;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridEntry {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub radius: f32,
+}
+
+#[derive(Resource)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<GridEntry>>,
+    // Largest radius of anything in the grid, how far past its cell an entry can reach
+    max_radius: f32,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        SpatialGrid::new(CELL_SIZE)
+    }
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        SpatialGrid { cell_size, cells: HashMap::default(), max_radius: 0.0 }
+    }
+
+    // Empties the grid but keeps the cells around, the next tick fills mostly the same ones
+    pub fn clear(&mut self) {
+        for entries in self.cells.values_mut() {
+            entries.clear();
+        }
+        self.max_radius = 0.0;
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec2, radius: f32) {
+        let cell = self.cell(position);
+        self.cells.entry(cell).or_default().push(GridEntry { entity, position, radius });
+        self.max_radius = self.max_radius.max(radius);
+    }
+
+    pub fn max_radius(&self) -> f32 {
+        self.max_radius
+    }
+
+    // Everything whose circle overlaps a circle of `radius` around `point`
+    pub fn within(&self, point: Vec2, radius: f32) -> impl Iterator<Item = GridEntry> + '_ {
+        let reach = Vec2::splat(radius + self.max_radius);
+        let min = self.cell(point - reach);
+        let max = self.cell(point + reach);
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .filter(move |entry| entry.position.distance(point) < radius + entry.radius)
+    }
+
+    // The entry with its center closest to `point`, if any is at most `max_distance` away
+    pub fn nearest(&self, point: Vec2, max_distance: f32) -> Option<GridEntry> {
+        let center = self.cell(point);
+        let rings = (max_distance / self.cell_size).ceil() as i32 + 1;
+        let mut nearest: Option<(f32, GridEntry)> = None;
+        for ring in 0..=rings {
+            for cell in ring_cells(center, ring) {
+                for entry in self.cells.get(&cell).into_iter().flatten() {
+                    let distance = entry.position.distance(point);
+                    if distance <= max_distance && nearest.is_none_or(|(closest, _)| distance < closest) {
+                        nearest = Some((distance, *entry));
+                    }
+                }
+            }
+            // Anything in the next ring is at least this far away
+            if nearest.is_some_and(|(closest, _)| closest <= ring as f32 * self.cell_size) {
+                break;
+            }
+        }
+        nearest.map(|(_, entry)| entry)
+    }
+
+    fn cell(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+}
+
+// The cells exactly `ring` cells away from `center`, counting diagonals as one
+fn ring_cells(center: IVec2, ring: i32) -> impl Iterator<Item = IVec2> {
+    (-ring..=ring)
+        .flat_map(move |x| (-ring..=ring).map(move |y| IVec2::new(x, y)))
+        .filter(move |offset| offset.x.abs().max(offset.y.abs()) == ring)
+        .map(move |offset| center + offset)
+}
+
+pub fn index_enemies(
+    mut grid: ResMut<SpatialGrid>,
+    query: Query<(Entity, &Transform, &Enemy), Without<Burrowed>>) {
+    grid.clear();
+    for (entity, transform, enemy) in query.iter() {
+        grid.insert(entity, transform.translation.truncate(), enemy.size);
+    }
+}
+
+fn index_spawned_enemies(
+    mut grid: ResMut<SpatialGrid>,
+    query: Query<(Entity, &Transform, &Enemy), (Added<Enemy>, Without<Burrowed>)>) {
+    for (entity, transform, enemy) in query.iter() {
+        grid.insert(entity, transform.translation.truncate(), enemy.size);
+    }
+}
+
+fn reset_grid(mut grid: ResMut<SpatialGrid>) {
+    *grid = SpatialGrid::default();
+}
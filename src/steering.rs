@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+
+// Steering behaviors for the enemy horde. Every enemy adds up a few wishes, each scaled by a
+// weight from its archetype: head for the player (seek), keep out of its neighbours' way
+// (separation), go where they are going (alignment) and stay with the group (cohesion).
+
+// Neighbours further than this many times their combined sizes are ignored
+const NEIGHBOR_RANGE: f32 = 4.0;
+// Separation only kicks in this many times their combined sizes apart
+const SEPARATION_RANGE: f32 = 1.5;
+// A crowd only reacts to a handful of the enemies around it, which keeps huge hordes cheap
+const MAX_NEIGHBORS: usize = 12;
+// Spreads out enemies standing exactly on top of each other in different directions
+const GOLDEN_ANGLE: f32 = 2.399_963;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SteeringWeights {
+    pub seek: f32,
+    pub separation: f32,
+    pub alignment: f32,
+    pub cohesion: f32,
+}
+
+// What an enemy knows about another one close by
+#[derive(Debug, Clone, Copy)]
+pub struct Neighbor {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub size: f32,
+}
+
+// How close another enemy has to be to be taken into account at all
+pub fn neighbor_distance(size: f32, other_size: f32) -> f32 {
+    (size + other_size) * NEIGHBOR_RANGE
+}
+
+// Direction to move in, at most of length 1. `id` tells apart enemies standing on the same spot.
+pub fn steer(
+    id: u32,
+    position: Vec2,
+    size: f32,
+    target: Vec2,
+    weights: SteeringWeights,
+    neighbors: impl Iterator<Item = Neighbor>,
+) -> Vec2 {
+    let seek = (target - position).normalize_or_zero();
+
+    // The grid hands neighbours over cell by cell, so pick the nearest ones in range
+    let mut nearby: Vec<(f32, Neighbor)> = neighbors
+        .map(|neighbor| (position.distance(neighbor.position), neighbor))
+        .filter(|(distance, neighbor)| *distance <= neighbor_distance(size, neighbor.size))
+        .collect();
+    if nearby.len() > MAX_NEIGHBORS {
+        nearby.select_nth_unstable_by(MAX_NEIGHBORS, |a, b| a.0.total_cmp(&b.0));
+        nearby.truncate(MAX_NEIGHBORS);
+    }
+
+    let mut separation = Vec2::ZERO;
+    let mut heading = Vec2::ZERO;
+    let mut center = Vec2::ZERO;
+    let count = nearby.len();
+    for (distance, neighbor) in nearby {
+        let offset = position - neighbor.position;
+        let reach = size + neighbor.size;
+        heading += neighbor.velocity;
+        center += neighbor.position;
+
+        let separation_distance = reach * SEPARATION_RANGE;
+        if distance < separation_distance {
+            let away = offset
+                .try_normalize()
+                .unwrap_or_else(|| Vec2::from_angle(id as f32 * GOLDEN_ANGLE));
+            // Pushes harder the more they overlap
+            separation += away * (1.0 - distance / separation_distance);
+        }
+    }
+
+    let mut direction = seek * weights.seek + separation * weights.separation;
+    if count > 0 {
+        direction += heading.normalize_or_zero() * weights.alignment;
+        direction += (center / count as f32 - position).normalize_or_zero() * weights.cohesion;
+    }
+    direction.clamp_length_max(1.0)
+}
@@ -0,0 +1,274 @@
+use std::{f32::consts::TAU, time::Duration};
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    reflect::TypePath,
+    utils::{thiserror::{self, Error}, BoxedFuture},
+};
+use rand::Rng;
+use serde::Deserialize;
+use crate::{arena::ArenaBounds, GameState, enemy::{self, EnemyAssets, EnemyKind, EnemyModifiers}, player::Player, rng::GameRng};
+
+// This plugin loads the wave schedule from `assets/` and spawns enemies according to it,
+// so waves can be balanced by editing the RON file instead of recompiling
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_asset::<WaveSchedule>()
+        .init_asset_loader::<WaveScheduleLoader>()
+        .insert_resource(WaveTimer {
+            timer: Timer::default(),
+            wave_duration: Timer::default(),
+            waves: 0,
+        })
+        .add_systems(Startup, load_wave_schedule)
+        .add_systems(OnExit(GameState::Menu), reset_wave_timer)
+        .add_systems(FixedUpdate, spawn_wave_enemies.run_if(in_state(GameState::Game)));
+    }
+}
+
+const WAVE_SCHEDULE_PATH: &str = "waves/default.waves.ron";
+
+
+// The whole schedule of a run. Once the last wave is over it keeps repeating.
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct WaveSchedule {
+    // Enemies never spawn closer than this to the player
+    pub min_spawn_distance: f32,
+    pub waves: Vec<WaveDefinition>
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct WaveDefinition {
+    // How long the wave lasts, in seconds
+    pub duration: f32,
+    // Time between two spawns of every group, in seconds
+    pub spawn_interval: f32,
+    pub spawns: Vec<SpawnGroup>,
+    #[serde(default = "default_multiplier")]
+    pub speed_multiplier: f32,
+    #[serde(default = "default_multiplier")]
+    pub health_multiplier: f32,
+    // Whether the boss shows up when this wave starts
+    #[serde(default)]
+    pub boss: bool,
+}
+
+// A number of enemies of one kind spawned together every spawn interval
+#[derive(Debug, Deserialize, Clone)]
+pub struct SpawnGroup {
+    // No default, a group with a missing or misspelled `enemy` fails to load
+    pub enemy: EnemyKind,
+    pub count: u32,
+    #[serde(default)]
+    pub pattern: SpawnPattern,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+pub enum SpawnPattern {
+    // Anywhere in the arena
+    #[default]
+    Scattered,
+    // Evenly spaced on a circle around the player
+    Ring { radius: f32 },
+    // Along the arena walls
+    Edges,
+    // Bunched up within `radius` of a random point of the arena, one point per group and spawn
+    Cluster { radius: f32 },
+}
+
+fn default_multiplier() -> f32 {
+    1.0
+}
+
+#[derive(Resource)]
+pub struct WaveScheduleHandle(pub Handle<WaveSchedule>);
+
+#[derive(Resource)]
+pub struct WaveTimer {
+    // Time between two spawns within the current wave
+    pub timer: Timer,
+    // Time left in the current wave
+    pub wave_duration: Timer,
+    // Number of waves finished so far this run
+    pub waves: u32
+}
+
+
+#[derive(Default)]
+pub struct WaveScheduleLoader;
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum WaveScheduleLoaderError {
+    #[error("Could not load wave schedule: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse wave schedule: {0}")]
+    RonSpannedError(#[from] ron::error::SpannedError),
+    #[error("Invalid wave {wave}: {reason}")]
+    InvalidWave { wave: usize, reason: &'static str },
+}
+
+impl WaveSchedule {
+    // Catches values the spawner can't turn into timers when the file is loaded,
+    // rather than every tick once the run is going
+    pub fn validate(&self) -> Result<(), WaveScheduleLoaderError> {
+        for (wave, definition) in self.waves.iter().enumerate() {
+            if !is_positive_duration(definition.duration) {
+                return Err(WaveScheduleLoaderError::InvalidWave { wave, reason: "duration must be a positive number of seconds" });
+            }
+            if !is_positive_duration(definition.spawn_interval) {
+                return Err(WaveScheduleLoaderError::InvalidWave { wave, reason: "spawn_interval must be a positive number of seconds" });
+            }
+            for group in &definition.spawns {
+                // The boss needs its health bar and phases, only `boss: true` brings it in
+                if group.enemy == EnemyKind::BossBee {
+                    return Err(WaveScheduleLoaderError::InvalidWave { wave, reason: "BossBee can't be spawned by a group, set boss: true instead" });
+                }
+                if let SpawnPattern::Cluster { radius } = group.pattern {
+                    if !(radius >= 0.0 && radius.is_finite()) {
+                        return Err(WaveScheduleLoaderError::InvalidWave { wave, reason: "cluster radius must be zero or more" });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_positive_duration(seconds: f32) -> bool {
+    seconds > 0.0 && Duration::try_from_secs_f32(seconds).is_ok()
+}
+
+impl AssetLoader for WaveScheduleLoader {
+    type Asset = WaveSchedule;
+    type Settings = ();
+    type Error = WaveScheduleLoaderError;
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let schedule = ron::de::from_bytes::<WaveSchedule>(&bytes)?;
+            schedule.validate()?;
+            Ok(schedule)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["waves.ron"]
+    }
+}
+
+
+fn load_wave_schedule(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(WaveScheduleHandle(asset_server.load(WAVE_SCHEDULE_PATH)));
+}
+
+fn reset_wave_timer(mut wave_timer: ResMut<WaveTimer>) {
+    // Zero durations make the first tick of the run pick up the first wave's timings
+    wave_timer.timer = Timer::default();
+    wave_timer.wave_duration = Timer::default();
+    wave_timer.waves = 0;
+}
+
+fn random_point(bounds: &ArenaBounds, rng: &mut impl Rng) -> Vec2 {
+    Vec2::new(
+        rng.gen_range(bounds.left..bounds.right),
+        rng.gen_range(bounds.bottom..bounds.top),
+    )
+}
+
+// `cluster_center` is shared by the whole group, so only the offset from it is random per enemy
+fn spawn_position(pattern: SpawnPattern, index: u32, count: u32, player_position: Vec2, cluster_center: Vec2, bounds: &ArenaBounds, rng: &mut impl Rng) -> Vec2 {
+    let position = match pattern {
+        SpawnPattern::Scattered => random_point(bounds, rng),
+        SpawnPattern::Ring { radius } => {
+            let angle = TAU * index as f32 / count as f32;
+            player_position + Vec2::from_angle(angle) * radius
+        }
+        SpawnPattern::Edges => {
+            let x = rng.gen_range(bounds.left..bounds.right);
+            let y = rng.gen_range(bounds.bottom..bounds.top);
+            match rng.gen_range(0..4) {
+                0 => Vec2::new(bounds.left, y),
+                1 => Vec2::new(bounds.right, y),
+                2 => Vec2::new(x, bounds.bottom),
+                _ => Vec2::new(x, bounds.top),
+            }
+        }
+        SpawnPattern::Cluster { radius } => {
+            let angle = rng.gen_range(0.0..TAU);
+            cluster_center + Vec2::from_angle(angle) * rng.gen_range(0.0..=radius)
+        }
+    };
+
+    bounds.clamp(position)
+}
+
+fn spawn_wave_enemies(mut commands: Commands,
+    enemy_assets: Res<EnemyAssets>,
+    time: Res<Time>,
+    schedule_handle: Res<WaveScheduleHandle>,
+    schedules: Res<Assets<WaveSchedule>>,
+    mut wave_timer: ResMut<WaveTimer>,
+    mut rng: ResMut<GameRng>,
+    bounds: Res<ArenaBounds>,
+    query: Query<&Transform, With<Player>>
+    ) {
+        // Nothing spawns until the schedule is loaded
+        let Some(schedule) = schedules.get(&schedule_handle.0) else {
+            return;
+        };
+        let Some(last_wave) = schedule.waves.len().checked_sub(1) else {
+            return;
+        };
+        let wave = &schedule.waves[(wave_timer.waves as usize).min(last_wave)];
+
+        // Set when a wave starts, and again when the schedule is edited so changes are picked up mid-wave
+        if schedules.is_changed() || wave_timer.wave_duration.duration().is_zero() {
+            wave_timer.timer.set_mode(TimerMode::Repeating);
+            wave_timer.timer.set_duration(Duration::from_secs_f32(wave.spawn_interval));
+            wave_timer.wave_duration.set_duration(Duration::from_secs_f32(wave.duration));
+        }
+
+        wave_timer.timer.tick(time.delta());
+        if wave_timer.wave_duration.tick(time.delta()).finished() {
+            wave_timer.waves += 1;
+            // Zero durations make the next tick pick up the next wave's timings
+            wave_timer.wave_duration = Timer::default();
+            wave_timer.timer = Timer::default();
+            return;
+        }
+
+        if !wave_timer.timer.just_finished() {
+            return;
+        }
+
+        let player_position = query.single().translation.truncate();
+        let modifiers = EnemyModifiers {
+            speed: wave.speed_multiplier,
+            health: wave.health_multiplier,
+            ..default()
+        };
+
+        for group in &wave.spawns {
+            let cluster_center = match group.pattern {
+                SpawnPattern::Cluster { .. } => random_point(&bounds, &mut rng.0),
+                _ => player_position,
+            };
+            for index in 0..group.count {
+                let position = spawn_position(group.pattern, index, group.count, player_position, cluster_center, &bounds, &mut rng.0);
+                if position.distance(player_position) > schedule.min_spawn_distance {
+                    enemy::spawn_enemy(&mut commands, &enemy_assets,
+                        group.enemy, position, modifiers);
+                }
+            }
+        }
+}
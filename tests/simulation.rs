@@ -0,0 +1,567 @@
+// Gameplay tests running the real plugins headless, one fixed tick per `App::update`
+use bevy::{ecs::system::RunSystemOnce, prelude::*, sprite::Mesh2dHandle};
+use game::{
+    arena::{ArenaBounds, ArenaExtent},
+    boss::Boss,
+    camera::{CameraController, CameraShake},
+    enemy::{self, Enemy, EnemyAssets, EnemyKind, EnemyModifiers, ENEMY_DAMAGE},
+    game::GamePlugin,
+    health::{Damaged, HealEvent, Health, Shield},
+    light::{LightAura, LIGHT_AURA_PULSE_SECONDS},
+    menu::MAX_VOLUME,
+    objective::RunProgress,
+    obstacle::{self, Destructible, PropKind},
+    persistence::parse_save,
+    progression::XpGem,
+    player::{Player, PLAYER_INITIAL_HIT_POINTS, PLAYER_INVULNERABILITY_SECONDS, PLAYER_SIZE},
+    rng::GameSeed,
+    simulation::HeadlessPlugin,
+    spatial::SpatialGrid,
+    steering::{self, Neighbor, SteeringWeights},
+    wave::{SpawnGroup, SpawnPattern, WaveDefinition, WaveSchedule, WaveScheduleHandle},
+    DisplayQuality, GameState, KeyBindings, Volume,
+};
+
+fn headless_app(schedule: WaveSchedule) -> App {
+    let mut app = App::new();
+    app.add_plugins((HeadlessPlugin, GamePlugin));
+    app.update();
+
+    // Swap the schedule loaded from `assets/` for the one the test needs
+    let handle = app.world.resource_mut::<Assets<WaveSchedule>>().add(schedule);
+    app.insert_resource(WaveScheduleHandle(handle));
+    app
+}
+
+// A schedule that never spawns anything, so the test controls every enemy
+fn no_waves() -> WaveSchedule {
+    WaveSchedule { min_spawn_distance: 0.0, waves: vec![] }
+}
+
+// A schedule with a single long wave spawning `spawns` every quarter second, at the normal
+// speed and health and without a boss
+fn one_wave(spawns: Vec<SpawnGroup>) -> WaveSchedule {
+    WaveSchedule {
+        min_spawn_distance: 0.0,
+        waves: vec![WaveDefinition {
+            duration: 60.0,
+            spawn_interval: 0.25,
+            spawns,
+            speed_multiplier: 1.0,
+            health_multiplier: 1.0,
+            boss: false,
+        }],
+    }
+}
+
+fn set_state(app: &mut App, state: GameState) {
+    app.world.resource_mut::<NextState<GameState>>().set(state);
+    app.update();
+}
+
+fn start_run(app: &mut App) {
+    set_state(app, GameState::Menu);
+    set_state(app, GameState::Game);
+}
+
+fn step(app: &mut App, ticks: u32) {
+    for _ in 0..ticks {
+        app.update();
+    }
+}
+
+fn state(app: &App) -> GameState {
+    *app.world.resource::<State<GameState>>().get()
+}
+
+fn player(app: &mut App) -> Entity {
+    app.world.query_filtered::<Entity, With<Player>>().single(&app.world)
+}
+
+fn player_position(app: &mut App) -> Vec2 {
+    let player = player(app);
+    app.world.get::<Transform>(player).unwrap().translation.truncate()
+}
+
+fn player_hit_points(app: &mut App) -> u32 {
+    let player = player(app);
+    app.world.get::<Health>(player).unwrap().hit_points
+}
+
+fn spawn_enemy(app: &mut App, kind: EnemyKind, position: Vec2) -> Entity {
+    spawn_enemy_with(app, kind, position, EnemyModifiers::default())
+}
+
+fn spawn_enemy_with(app: &mut App, kind: EnemyKind, position: Vec2, modifiers: EnemyModifiers) -> Entity {
+    app.world.run_system_once_with((kind, position, modifiers),
+        |In((kind, position, modifiers)): In<(EnemyKind, Vec2, EnemyModifiers)>,
+         mut commands: Commands,
+         enemy_assets: Res<EnemyAssets>| {
+            enemy::spawn_enemy(&mut commands, &enemy_assets, kind, position, modifiers)
+        })
+}
+
+fn spawn_prop(app: &mut App, kind: PropKind, position: Vec2) -> Entity {
+    app.world.run_system_once_with((kind, position),
+        |In((kind, position)): In<(PropKind, Vec2)>, mut commands: Commands, asset_server: Res<AssetServer>| {
+            obstacle::spawn_prop(&mut commands, &asset_server, kind, position)
+        })
+}
+
+// Starts a run without waves, lets `prepare` set up the player, then drops a kobold right on the
+// player and steps once so it touches
+fn run_with_enemy_at_player(prepare: impl FnOnce(&mut App, Entity)) -> App {
+    let mut app = headless_app(no_waves());
+    start_run(&mut app);
+    let player = player(&mut app);
+    prepare(&mut app, player);
+    let position = player_position(&mut app);
+
+    spawn_enemy(&mut app, EnemyKind::Kobold, position);
+    step(&mut app, 1);
+    app
+}
+
+fn enemy_positions(app: &mut App) -> Vec<Vec2> {
+    app.world
+        .query_filtered::<&Transform, With<Enemy>>()
+        .iter(&app.world)
+        .map(|transform| transform.translation.truncate())
+        .collect()
+}
+
+#[test]
+fn player_touching_an_enemy_loses_enemy_damage() {
+    let mut app = run_with_enemy_at_player(|_, _| {});
+
+    assert_eq!(player_hit_points(&mut app), PLAYER_INITIAL_HIT_POINTS - ENEMY_DAMAGE);
+    assert!(enemy_positions(&mut app).is_empty());
+}
+
+#[test]
+fn player_at_zero_health_loses_the_game() {
+    let mut app = run_with_enemy_at_player(|app, player| {
+        app.world.get_mut::<Health>(player).unwrap().hit_points = ENEMY_DAMAGE;
+    });
+    step(&mut app, 2);
+
+    assert_eq!(state(&app), GameState::GameLost);
+}
+
+#[test]
+fn damage_past_zero_stops_at_zero() {
+    let mut app = run_with_enemy_at_player(|app, player| {
+        app.world.get_mut::<Health>(player).unwrap().hit_points = ENEMY_DAMAGE / 2;
+    });
+
+    assert_eq!(player_hit_points(&mut app), 0);
+    step(&mut app, 1);
+    assert_eq!(state(&app), GameState::GameLost);
+}
+
+#[test]
+fn player_is_invulnerable_for_a_moment_after_a_hit() {
+    // Two enemies touch the player at once
+    let mut app = run_with_enemy_at_player(|app, player| {
+        app.world.get_mut::<LightAura>(player).unwrap().damage = 0;
+        let position = player_position(app);
+        spawn_enemy(app, EnemyKind::Kobold, position);
+    });
+
+    // Only one hit lands, the other enemy waits out the i-frames instead of being used up
+    assert_eq!(enemy_positions(&mut app).len(), 1);
+    assert_eq!(player_hit_points(&mut app), PLAYER_INITIAL_HIT_POINTS - ENEMY_DAMAGE);
+    step(&mut app, (PLAYER_INVULNERABILITY_SECONDS * 64.0) as u32 / 2);
+    assert_eq!(enemy_positions(&mut app).len(), 1);
+
+    step(&mut app, (PLAYER_INVULNERABILITY_SECONDS * 64.0) as u32);
+    // Then it hits, with maybe a point regenerated in between
+    assert!(enemy_positions(&mut app).is_empty());
+    assert!(player_hit_points(&mut app) < PLAYER_INITIAL_HIT_POINTS - ENEMY_DAMAGE);
+}
+
+#[test]
+fn shield_takes_hits_first_and_overhealing_tops_it_up() {
+    let mut app = run_with_enemy_at_player(|app, player| {
+        app.world.entity_mut(player).insert(Shield::new(ENEMY_DAMAGE + 5, 0.0, 10.0));
+    });
+    let player = player(&mut app);
+    assert_eq!(player_hit_points(&mut app), PLAYER_INITIAL_HIT_POINTS);
+    assert_eq!(app.world.get::<Shield>(player).unwrap().points, 5);
+
+    app.world.get_mut::<Health>(player).unwrap().hit_points = PLAYER_INITIAL_HIT_POINTS - 5;
+    app.world.send_event(HealEvent { target: player, amount: 8 });
+    step(&mut app, 1);
+    assert_eq!(player_hit_points(&mut app), PLAYER_INITIAL_HIT_POINTS);
+    assert_eq!(app.world.get::<Shield>(player).unwrap().points, 8);
+}
+
+#[test]
+fn a_hit_the_shield_soaks_up_still_counts_as_a_hit() {
+    let mut app = run_with_enemy_at_player(|app, player| {
+        app.world.entity_mut(player).insert(Shield::new(ENEMY_DAMAGE * 2, 0.0, 10.0));
+    });
+    let player = player(&mut app);
+    assert_eq!(player_hit_points(&mut app), PLAYER_INITIAL_HIT_POINTS);
+
+    let damaged = app.world.resource::<Events<Damaged>>();
+    let hits: Vec<Damaged> = damaged.get_reader().read(damaged).copied().collect();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].entity, player);
+    assert_eq!(hits[0].amount, ENEMY_DAMAGE);
+    let shakes = app.world.resource::<Events<CameraShake>>();
+    assert_eq!(shakes.get_reader().read(shakes).count(), 1);
+}
+
+#[test]
+fn light_aura_kills_enemies_in_range() {
+    let mut app = headless_app(no_waves());
+    start_run(&mut app);
+    let position = player_position(&mut app);
+
+    let enemy = spawn_enemy(&mut app, EnemyKind::Kobold, position + Vec2::new(50.0, 0.0));
+    app.world.get_mut::<Enemy>(enemy).unwrap().speed = 0.0;
+    // A kobold takes four pulses, leave a little slack for the first one
+    let ticks_per_pulse = (LIGHT_AURA_PULSE_SECONDS * 64.0) as u32;
+    step(&mut app, ticks_per_pulse * 5);
+
+    assert!(app.world.get_entity(enemy).is_none());
+    assert_eq!(app.world.resource::<RunProgress>().kills, 1);
+    assert_eq!(player_hit_points(&mut app), PLAYER_INITIAL_HIT_POINTS);
+}
+
+#[test]
+fn player_cannot_walk_out_of_the_arena() {
+    let mut app = headless_app(no_waves());
+    start_run(&mut app);
+
+    // None of the arenas have obstacles straight above where the player starts
+    app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::W);
+    step(&mut app, 200);
+
+    let bounds = *app.world.resource::<ArenaBounds>();
+    assert_eq!(player_position(&mut app).y, bounds.top - PLAYER_SIZE / 2.0);
+}
+
+#[test]
+fn camera_follows_the_player_but_stays_inside_the_arena() {
+    let mut app = headless_app(no_waves());
+    let camera = app.world.spawn((
+        Transform::default(),
+        OrthographicProjection::default(),
+        CameraController::default(),
+    )).id();
+    start_run(&mut app);
+
+    app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::W);
+    step(&mut app, 200);
+
+    let camera_position = app.world.get::<Transform>(camera).unwrap().translation.truncate();
+    let extent = app.world.resource::<ArenaExtent>().0;
+    assert!(camera_position.y > 0.0);
+    assert!(camera_position.y <= extent.top);
+    assert!((player_position(&mut app) - camera_position).y.abs() < 100.0);
+}
+
+#[test]
+fn enemies_walk_around_obstacles_in_their_way() {
+    let mut app = headless_app(no_waves());
+    start_run(&mut app);
+    // The enemy has to make it all the way to the player
+    let player = player(&mut app);
+    app.world.get_mut::<LightAura>(player).unwrap().damage = 0;
+    let position = player_position(&mut app);
+
+    spawn_prop(&mut app, PropKind::Rock, position + Vec2::new(75.0, 0.0));
+    spawn_enemy(&mut app, EnemyKind::Kobold, position + Vec2::new(150.0, 0.0));
+    step(&mut app, 300);
+
+    // Gone by running into the player, not stuck behind the rock
+    assert!(enemy_positions(&mut app).is_empty());
+    assert!(player_hit_points(&mut app) < PLAYER_INITIAL_HIT_POINTS);
+}
+
+#[test]
+fn enemies_spawned_together_spread_out_while_chasing() {
+    let mut app = headless_app(no_waves());
+    start_run(&mut app);
+    let player = player(&mut app);
+    app.world.get_mut::<LightAura>(player).unwrap().damage = 0;
+    // Nothing stands above the player's spawn in any arena
+    let player_start = player_position(&mut app);
+    let start = player_start + Vec2::new(0.0, 250.0);
+    for _ in 0..8 {
+        spawn_enemy(&mut app, EnemyKind::Kobold, start);
+    }
+    step(&mut app, 30);
+
+    let positions = enemy_positions(&mut app);
+    assert_eq!(positions.len(), 8);
+    let size = EnemyKind::Kobold.archetype().size;
+    // Still chasing as a group, not just scattering
+    let center = positions.iter().sum::<Vec2>() / positions.len() as f32;
+    assert!(center.distance(player_start) < start.distance(player_start) - size);
+    for (i, a) in positions.iter().enumerate() {
+        assert!(a.distance(player_start) < start.distance(player_start));
+        for b in &positions[i + 1..] {
+            assert!(a.distance(*b) > size, "{a} and {b} still overlap");
+        }
+    }
+}
+
+#[test]
+fn spatial_grid_finds_enemies_near_a_point() {
+    let mut app = headless_app(no_waves());
+    start_run(&mut app);
+    let player = player(&mut app);
+    app.world.get_mut::<LightAura>(player).unwrap().damage = 0;
+    let position = player_position(&mut app);
+
+    let near = spawn_enemy(&mut app, EnemyKind::Kobold, position + Vec2::new(0.0, 150.0));
+    let far = spawn_enemy(&mut app, EnemyKind::Kobold, position + Vec2::new(0.0, 280.0));
+    step(&mut app, 1);
+
+    let grid = app.world.resource::<SpatialGrid>();
+    let within: Vec<Entity> = grid.within(position, 200.0).map(|entry| entry.entity).collect();
+    assert_eq!(within, vec![near]);
+    assert_eq!(grid.nearest(position, 1000.0).map(|entry| entry.entity), Some(near));
+    assert_eq!(grid.nearest(position + Vec2::new(0.0, 300.0), 1000.0).map(|entry| entry.entity), Some(far));
+    assert_eq!(grid.nearest(position, 100.0), None);
+}
+
+#[test]
+fn steering_reacts_to_the_nearest_neighbors() {
+    let weights = SteeringWeights { seek: 0.0, separation: 1.0, alignment: 0.0, cohesion: 0.0 };
+    let neighbor = |position: Vec2| Neighbor { position, velocity: Vec2::ZERO, size: 10.0 };
+    // A crowd in range but too far to push, listed before the one bumping into us
+    let neighbors = (0..20)
+        .map(|_| neighbor(Vec2::new(-60.0, 0.0)))
+        .chain([neighbor(Vec2::new(5.0, 0.0))]);
+
+    let direction = steering::steer(0, Vec2::ZERO, 10.0, Vec2::ZERO, weights, neighbors);
+    assert!(direction.x < 0.0, "{direction} doesn't move away from the closest neighbor");
+}
+
+#[test]
+fn light_breaks_props_and_opens_chests() {
+    let mut app = headless_app(no_waves());
+    start_run(&mut app);
+    let position = player_position(&mut app);
+
+    let barrel = spawn_prop(&mut app, PropKind::Barrel, position + Vec2::new(0.0, 60.0));
+    let chest = spawn_prop(&mut app, PropKind::Chest, position + Vec2::new(60.0, 0.0));
+    let ticks_per_pulse = (LIGHT_AURA_PULSE_SECONDS * 64.0) as u32;
+    step(&mut app, ticks_per_pulse * 7);
+
+    assert!(app.world.get_entity(barrel).is_none());
+    // The chest stays, opened
+    assert!(app.world.get::<Destructible>(chest).is_none());
+    let texture = app.world.get::<Handle<Image>>(chest).unwrap();
+    let path = app.world.resource::<AssetServer>().get_path(texture).unwrap();
+    assert!(path.to_string().ends_with("generic-rpg-trasure-open.png"));
+}
+
+#[test]
+fn pausing_freezes_gameplay_and_resuming_keeps_the_player() {
+    let mut app = headless_app(no_waves());
+    start_run(&mut app);
+    let position = player_position(&mut app);
+    spawn_enemy(&mut app, EnemyKind::Kobold, position + Vec2::new(300.0, 0.0));
+    step(&mut app, 5);
+
+    set_state(&mut app, GameState::Pause);
+    let paused_positions = enemy_positions(&mut app);
+    step(&mut app, 30);
+    assert_eq!(enemy_positions(&mut app), paused_positions);
+
+    set_state(&mut app, GameState::Game);
+    step(&mut app, 5);
+    assert_ne!(enemy_positions(&mut app), paused_positions);
+    assert_eq!(app.world.query::<&Player>().iter(&app.world).count(), 1);
+}
+
+#[test]
+fn same_seed_spawns_the_same_enemies() {
+    let run = || {
+        let mut app = headless_app(one_wave(vec![SpawnGroup {
+            enemy: EnemyKind::Kobold,
+            count: 10,
+            pattern: SpawnPattern::Scattered,
+        }]));
+        app.insert_resource(GameSeed { requested: Some(12345), ..default() });
+        start_run(&mut app);
+        step(&mut app, 20);
+        enemy_positions(&mut app)
+    };
+
+    let first = run();
+    assert!(!first.is_empty());
+    assert_eq!(first, run());
+}
+
+#[test]
+fn wave_schedules_with_unusable_timings_are_rejected() {
+    let schedule = |duration: f32, spawn_interval: f32| {
+        let mut schedule = one_wave(vec![]);
+        schedule.waves[0].duration = duration;
+        schedule.waves[0].spawn_interval = spawn_interval;
+        schedule
+    };
+
+    assert!(schedule(30.0, 0.5).validate().is_ok());
+    assert!(schedule(-30.0, 0.5).validate().is_err());
+    assert!(schedule(30.0, 0.0).validate().is_err());
+    assert!(schedule(f32::NAN, 0.5).validate().is_err());
+    assert!(schedule(30.0, f32::INFINITY).validate().is_err());
+}
+
+#[test]
+fn spawn_groups_must_name_their_enemy() {
+    assert!(ron::from_str::<SpawnGroup>("(enemy: Fox, count: 3)").is_ok());
+    assert!(ron::from_str::<SpawnGroup>("(count: 3)").is_err());
+    assert!(ron::from_str::<SpawnGroup>("(enmy: Fox, count: 3)").is_err());
+}
+
+#[test]
+fn wave_schedules_cannot_spawn_the_boss_as_a_regular_enemy() {
+    let schedule = one_wave(vec![SpawnGroup {
+        enemy: EnemyKind::BossBee,
+        count: 1,
+        pattern: SpawnPattern::Scattered,
+    }]);
+    assert!(schedule.validate().is_err());
+}
+
+#[test]
+fn cluster_groups_spawn_bunched_up() {
+    let mut schedule = one_wave(vec![SpawnGroup {
+        enemy: EnemyKind::Kobold,
+        count: 6,
+        pattern: SpawnPattern::Cluster { radius: 10.0 },
+    }]);
+    schedule.waves[0].speed_multiplier = 0.0;
+    let mut app = headless_app(schedule);
+    start_run(&mut app);
+    while enemy_positions(&mut app).is_empty() {
+        step(&mut app, 1);
+    }
+
+    let positions = enemy_positions(&mut app);
+    assert_eq!(positions.len(), 6);
+    for (i, a) in positions.iter().enumerate() {
+        for b in &positions[i + 1..] {
+            assert!(a.distance(*b) <= 20.0, "{a} and {b} are not in one cluster");
+        }
+    }
+}
+
+#[test]
+fn split_pieces_keep_the_wave_speed() {
+    let mut app = headless_app(no_waves());
+    start_run(&mut app);
+    let player = player(&mut app);
+    app.world.get_mut::<LightAura>(player).unwrap().damage = 0;
+    let position = player_position(&mut app) + Vec2::new(0.0, 250.0);
+
+    let modifiers = EnemyModifiers { speed: 2.0, ..default() };
+    let slime = spawn_enemy_with(&mut app, EnemyKind::BlueSlime, position, modifiers);
+    app.world.get_mut::<Health>(slime).unwrap().hit_points = 0;
+    step(&mut app, 1);
+
+    let speeds: Vec<f32> = app.world.query::<&Enemy>().iter(&app.world).map(|enemy| enemy.speed).collect();
+    assert_eq!(speeds.len(), 2);
+    for speed in speeds {
+        assert_eq!(speed, EnemyKind::BlueSlime.archetype().speed * 2.0);
+    }
+}
+
+#[test]
+fn out_of_range_save_values_fall_back_to_defaults() {
+    let save = parse_save(&format!(
+        "(version: 1, display_quality: High, volume: ({}), key_bindings: (pause: []))",
+        MAX_VOLUME + 1
+    )).unwrap();
+    assert_eq!(save.volume, Volume::default());
+    assert_eq!(save.key_bindings.pause, KeyBindings::default().pause);
+    // Values that are fine are kept
+    assert_eq!(save.display_quality, DisplayQuality::High);
+
+    assert!(parse_save("(version: 1, display_quality: Ultra)").is_err());
+}
+
+#[test]
+fn experience_gems_share_one_mesh_and_material() {
+    let mut app = headless_app(no_waves());
+    start_run(&mut app);
+    let position = player_position(&mut app) + Vec2::new(0.0, 250.0);
+    for offset in [-50.0, 50.0] {
+        let enemy = spawn_enemy(&mut app, EnemyKind::Kobold, position + Vec2::new(offset, 0.0));
+        app.world.get_mut::<Health>(enemy).unwrap().hit_points = 0;
+    }
+    let meshes = app.world.resource::<Assets<Mesh>>().len();
+    step(&mut app, 1);
+
+    let gems: Vec<(Mesh2dHandle, Handle<ColorMaterial>)> = app.world
+        .query_filtered::<(&Mesh2dHandle, &Handle<ColorMaterial>), With<XpGem>>()
+        .iter(&app.world)
+        .map(|(mesh, material)| (mesh.clone(), material.clone()))
+        .collect();
+    assert_eq!(gems.len(), 2);
+    assert_eq!(gems[0], gems[1]);
+    assert_eq!(app.world.resource::<Assets<Mesh>>().len(), meshes);
+}
+
+#[test]
+fn boss_is_scaled_by_its_wave() {
+    let mut schedule = one_wave(vec![]);
+    schedule.waves[0].speed_multiplier = 1.5;
+    schedule.waves[0].health_multiplier = 2.0;
+    schedule.waves[0].boss = true;
+    let mut app = headless_app(schedule);
+    start_run(&mut app);
+    step(&mut app, 1);
+
+    let (enemy, health) = app.world
+        .query_filtered::<(&Enemy, &Health), With<Boss>>()
+        .single(&app.world);
+    let archetype = EnemyKind::BossBee.archetype();
+    assert_eq!(health.max_hit_points, archetype.hit_points * 2);
+    assert_eq!(enemy.speed, archetype.speed * 1.5);
+}
+
+#[test]
+fn replaying_reuses_the_last_seed() {
+    let mut app = headless_app(no_waves());
+    // The menu that clears out the last run isn't part of the headless app
+    let restart = |app: &mut App| {
+        let player = player(app);
+        app.world.despawn(player);
+        start_run(app);
+    };
+    start_run(&mut app);
+    let first = app.world.resource::<GameSeed>().current;
+
+    app.world.resource_mut::<GameSeed>().replay = true;
+    restart(&mut app);
+    assert_eq!(app.world.resource::<GameSeed>().current, first);
+    assert!(!app.world.resource::<GameSeed>().replay);
+
+    restart(&mut app);
+    assert_ne!(app.world.resource::<GameSeed>().current, first);
+}
+
+#[test]
+fn starting_another_run_reuses_the_player_assets() {
+    let mut app = headless_app(no_waves());
+    start_run(&mut app);
+    let atlases = app.world.resource::<Assets<TextureAtlas>>().len();
+    let meshes = app.world.resource::<Assets<Mesh>>().len();
+    let materials = app.world.resource::<Assets<ColorMaterial>>().len();
+
+    let player = player(&mut app);
+    app.world.despawn(player);
+    start_run(&mut app);
+    assert_eq!(app.world.resource::<Assets<TextureAtlas>>().len(), atlases);
+    assert_eq!(app.world.resource::<Assets<Mesh>>().len(), meshes);
+    assert_eq!(app.world.resource::<Assets<ColorMaterial>>().len(), materials);
+}